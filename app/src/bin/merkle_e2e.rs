@@ -1,17 +1,29 @@
 use alloy::primitives::{FixedBytes, U256};
 use alloy::providers::builder;
 use app::utils::*;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use eyre::Result;
-use foundry_contracts::merkleverifier::MerkleVerifier;
-use merkle_tree::tree::MerkleTree;
+use foundry_contracts::{
+    merkleverifier::MerkleVerifier, sha256merkleverifier::Sha256MerkleVerifier,
+};
+use merkle_tree::hasher::{Blake3, Keccak256, MerkleHasher, Poseidon, Sha256};
+use merkle_tree::poseidon::{fr_to_hash, hash_to_fr};
+use merkle_tree::tree::{HashDomain, MerkleTree, Padding};
 use rand::Rng;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{
     fs::{self, OpenOptions},
     io::Write,
 };
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Hasher {
+    Keccak256,
+    Sha256,
+    Blake3,
+    Poseidon,
+}
+
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -21,8 +33,57 @@ struct Args {
     #[arg(long, value_parser, value_delimiter = ',')]
     sizes: Vec<usize>,
 
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "keccak256")]
+    hashers: Vec<Hasher>,
+
     #[arg(long)]
     output: String,
+
+    /// Seed of the committed dataset, shared with the other e2e benchmark
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+struct Opening {
+    root: [u8; 32],
+    value: Vec<u8>,
+    proof: Vec<FixedBytes<32>>,
+    result: bool,
+    commit_time: Duration,
+    open_time: Duration,
+}
+
+fn commit_and_open<H: MerkleHasher>(
+    width: usize,
+    vec: &[&Vec<u8>],
+    index: usize,
+) -> Result<Opening> {
+    let mut tree: MerkleTree<H> =
+        MerkleTree::setup_with_hasher(width, HashDomain::Plain, Padding::RepeatLast)?;
+
+    // Commit
+    let commit_time_start = Instant::now();
+    tree.commit(vec)?;
+    let commit_time = Instant::now() - commit_time_start;
+
+    // Open and generate proof
+    let open_time_start = Instant::now();
+    let (value, proof) = tree.open(index)?;
+    let open_time = Instant::now() - open_time_start;
+    let result = tree.verify(index, &value, &proof);
+
+    Ok(Opening {
+        root: tree.root_hash().unwrap(),
+        value,
+        proof: proof
+            .hashes
+            .iter()
+            .map(|p| FixedBytes::from_slice(p))
+            .collect(),
+        result,
+        commit_time,
+        open_time,
+    })
 }
 
 #[tokio::main]
@@ -30,91 +91,119 @@ async fn main() -> Result<()> {
     let args = Args::parse();
     let provider = builder().with_recommended_fillers().on_anvil_with_wallet();
 
-    let dir = format!("{}/merkle_e2e", args.output);
-    if !std::path::Path::new(&dir).exists() {
-        fs::create_dir_all(&dir)?;
-    }
-
-    for size in args.sizes.iter() {
-        let file_path = format!("{}/merkle_{}.csv", dir, size);
-        let mut file = if !fs::metadata(file_path.clone()).is_ok() {
-            let mut file = OpenOptions::new()
-                .append(true)
-                .create(true)
-                .open(file_path)?;
-            writeln!(
-                file,
-                "size,width,result,gas,proof_size,commit_time,open_time"
-            )?;
-            file
-        } else {
-            OpenOptions::new().append(true).open(file_path)?
+    for hasher in args.hashers.iter() {
+        // Keccak-256 results keep the directory read by plot_merkle.py
+        let dir = match hasher {
+            Hasher::Keccak256 => format!("{}/merkle_e2e", args.output),
+            Hasher::Sha256 => format!("{}/merkle_e2e_sha256", args.output),
+            Hasher::Blake3 => format!("{}/merkle_e2e_blake3", args.output),
+            Hasher::Poseidon => format!("{}/merkle_e2e_poseidon", args.output),
         };
-        for width in args.widths.iter() {
-            println!("################################");
-            println!("Size: {:?}, Width: {:?}", size, width);
-
-            let mut tree = MerkleTree::setup(width.clone());
-
-            // Deploy the contract
-            let contract = MerkleVerifier::deploy(&provider, U256::from(width.clone())).await?;
-
-            // Generate random vector
-            let vec = (0..size.clone())
-                .map(|_| random_bytes32())
-                .collect::<Vec<_>>();
-            let vec = vec.iter().collect::<Vec<_>>();
-
-            // Commit
-            let commit_time_start = Instant::now();
-            tree.commit(vec.as_slice());
-            let commit_time = Instant::now() - commit_time_start;
-            let com = tree.root_hash().unwrap();
-            let builder = contract.commit(FixedBytes::from_slice(&com));
-            let _ = builder.send().await?.watch().await?;
-
-            // Open and generate proof
-            let random_index = rand::thread_rng().gen_range(0..size.clone());
-            let open_time_start = Instant::now();
-            let (value, proof) = tree.open(random_index);
-            let open_time = Instant::now() - open_time_start;
-            let proof = proof
-                .hashes
-                .iter()
-                .map(|p| FixedBytes::from_slice(p))
-                .collect::<Vec<_>>();
-
-            // Proof size in bytes
-            let proof_size = 32 * proof.len();
-
-            // Verify the proof on the contract
-            let builder = contract.verify(
-                U256::from(random_index),
-                U256::from_be_bytes(to_bytes32_array(&value)),
-                proof,
-            );
-            let result = builder.call().await?._0;
-            let gas = builder.estimate_gas().await?;
-
-            println!(
-                "Result: {}, Gas: {}, Proof size: {}, Commit time: {}, Open time: {}",
-                result,
-                gas,
-                proof_size,
-                commit_time.as_millis(),
-                open_time.as_millis()
-            );
-            writeln!(
-                file,
-                "{},{},{},{},{},{},{}",
-                size,
-                width,
-                result,
-                gas,
-                proof_size,
-                commit_time.as_millis(),
-                open_time.as_millis()
-            )?;
+        if !std::path::Path::new(&dir).exists() {
+            fs::create_dir_all(&dir)?;
+        }
+
+        for size in args.sizes.iter() {
+            let file_path = format!("{}/merkle_{}.csv", dir, size);
+            let mut file = if !fs::metadata(file_path.clone()).is_ok() {
+                let mut file = OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(file_path)?;
+                writeln!(
+                    file,
+                    "size,width,result,gas,proof_size,commit_time,open_time"
+                )?;
+                file
+            } else {
+                OpenOptions::new().append(true).open(file_path)?
+            };
+            for width in args.widths.iter() {
+                println!("################################");
+                println!("Hasher: {:?}, Size: {:?}, Width: {:?}", hasher, size, width);
+
+                // Poseidon absorbs a whole node in one permutation, which bounds the width
+                if *hasher == Hasher::Poseidon
+                    && Poseidon::max_width(HashDomain::Plain).is_some_and(|max| *width > max)
+                {
+                    println!("Skipped, Poseidon does not support this width");
+                    continue;
+                }
+
+                // Commit the same 32-byte values as verkle_e2e run with the same seed. Poseidon
+                // leaves must be canonical field elements, so they are reduced modulo the field order.
+                let vec = random_dataset(*size, args.seed);
+                let vec = match hasher {
+                    Hasher::Poseidon => vec
+                        .iter()
+                        .map(|value| fr_to_hash(&hash_to_fr(&to_bytes32_array(value))).to_vec())
+                        .collect(),
+                    _ => vec,
+                };
+                let vec = vec.iter().collect::<Vec<_>>();
+
+                let random_index = rand::thread_rng().gen_range(0..size.clone());
+                let opening = match hasher {
+                    Hasher::Keccak256 => commit_and_open::<Keccak256>(*width, &vec, random_index)?,
+                    Hasher::Sha256 => commit_and_open::<Sha256>(*width, &vec, random_index)?,
+                    Hasher::Blake3 => commit_and_open::<Blake3>(*width, &vec, random_index)?,
+                    Hasher::Poseidon => commit_and_open::<Poseidon>(*width, &vec, random_index)?,
+                };
+
+                // Proof size in bytes
+                let proof_size = 32 * opening.proof.len();
+
+                // Verify the proof on the contract. There is no BLAKE3 or Poseidon verifier, so
+                // their result is the local one and their gas is left empty.
+                let index = U256::from(random_index);
+                let value = U256::from_be_bytes(to_bytes32_array(&opening.value));
+                let root = FixedBytes::from_slice(&opening.root);
+                let (result, gas) = match hasher {
+                    Hasher::Keccak256 => {
+                        let contract =
+                            MerkleVerifier::deploy(&provider, U256::from(width.clone())).await?;
+                        let _ = contract.commit(root).send().await?.watch().await?;
+                        let builder = contract.verify(index, value, opening.proof);
+                        (
+                            builder.call().await?._0,
+                            Some(builder.estimate_gas().await?),
+                        )
+                    }
+                    Hasher::Sha256 => {
+                        let contract =
+                            Sha256MerkleVerifier::deploy(&provider, U256::from(width.clone()))
+                                .await?;
+                        let _ = contract.commit(root).send().await?.watch().await?;
+                        let builder = contract.verify(index, value, opening.proof);
+                        (
+                            builder.call().await?._0,
+                            Some(builder.estimate_gas().await?),
+                        )
+                    }
+                    Hasher::Blake3 | Hasher::Poseidon => (opening.result, None),
+                };
+                let gas = gas.map(|gas| gas.to_string()).unwrap_or_default();
+
+                println!(
+                    "Result: {}, Gas: {}, Proof size: {}, Commit time: {}, Open time: {}",
+                    result,
+                    gas,
+                    proof_size,
+                    opening.commit_time.as_millis(),
+                    opening.open_time.as_millis()
+                );
+                writeln!(
+                    file,
+                    "{},{},{},{},{},{},{}",
+                    size,
+                    width,
+                    result,
+                    gas,
+                    proof_size,
+                    opening.commit_time.as_millis(),
+                    opening.open_time.as_millis()
+                )?;
+            }
         }
     }
 
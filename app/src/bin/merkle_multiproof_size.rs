@@ -0,0 +1,93 @@
+use clap::Parser;
+use eyre::Result;
+use merkle_tree::tree::MerkleTree;
+use rand::{seq::index, Rng};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(long, value_parser, value_delimiter = ',')]
+    widths: Vec<usize>,
+
+    #[arg(long, value_parser, value_delimiter = ',')]
+    sizes: Vec<usize>,
+
+    #[arg(long, value_parser, value_delimiter = ',')]
+    openings: Vec<usize>,
+
+    #[arg(long)]
+    output: String,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut rng = rand::thread_rng();
+
+    if !std::path::Path::new(&args.output).exists() {
+        fs::create_dir_all(&args.output)?;
+    }
+
+    let file_path = format!("{}/merkle_multiproof_size.csv", args.output);
+    let mut file = if !fs::metadata(file_path.clone()).is_ok() {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(file_path)?;
+        writeln!(
+            file,
+            "size,width,openings,single_proofs_size,multiproof_size"
+        )?;
+        file
+    } else {
+        OpenOptions::new().append(true).open(file_path)?
+    };
+
+    for size in args.sizes.iter() {
+        let values: Vec<Vec<u8>> = (0..*size)
+            .map(|_| (0..32).map(|_| rng.gen()).collect())
+            .collect();
+        let values = values.iter().collect::<Vec<_>>();
+
+        for width in args.widths.iter() {
+            let mut tree = MerkleTree::setup(*width);
+            tree.commit(values.as_slice())?;
+
+            for openings in args.openings.iter().filter(|openings| *openings <= size) {
+                println!("################################");
+                println!(
+                    "Size: {:?}, Width: {:?}, Openings: {:?}",
+                    size, width, openings
+                );
+
+                let mut indices = index::sample(&mut rng, *size, *openings).into_vec();
+                indices.sort();
+
+                // Proof sizes in bytes, with the flags of the multiproof packed as bits
+                let mut single_proofs_size = 0;
+                for index in indices.iter() {
+                    let (_, proof) = tree.open(*index)?;
+                    single_proofs_size += 32 * proof.hashes.len();
+                }
+                let (_, multiproof) = tree.open_many(&indices)?;
+                let multiproof_size =
+                    32 * multiproof.hashes.len() + multiproof.flags.len().div_ceil(8);
+
+                println!(
+                    "Single proofs size: {}, Multiproof size: {}",
+                    single_proofs_size, multiproof_size
+                );
+                writeln!(
+                    file,
+                    "{},{},{},{},{}",
+                    size, width, openings, single_proofs_size, multiproof_size
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use alloy::primitives::U256;
 use alloy::providers::builder;
-use app::utils::{curve_to_u256_vec, scalar_to_u256};
+use app::utils::{curve_to_u256_vec, random_dataset, scalar_to_u256, to_verkle_proof};
 use ark_bn254::Fr;
 use ark_ec::{AffineRepr, CurveGroup};
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
@@ -9,7 +9,7 @@ use clap::Parser;
 use eyre::Result;
 use foundry_contracts::verkleverifier::{
     Curve::{G1Point, G2Point},
-    VerkleVerifier::{self, Multiproof, VerkleProof},
+    VerkleVerifier,
 };
 use rand::Rng;
 use std::{
@@ -19,7 +19,7 @@ use std::{
 };
 
 use std::time::Instant;
-use verkle_tree::tree::VerkleTree;
+use verkle_tree::{data_structures::Opening, tree::VerkleTree};
 type Domain = GeneralEvaluationDomain<Fr>;
 
 #[derive(Parser, Debug, Clone)]
@@ -33,6 +33,10 @@ struct Args {
 
     #[arg(long)]
     output: String,
+
+    /// Seed of the committed dataset, shared with the other e2e benchmark
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
 }
 
 #[tokio::main]
@@ -55,7 +59,7 @@ async fn main() -> Result<()> {
                 .open(file_path)?;
             writeln!(
                 file,
-                "size,width,result,gas,proof_size,commit_time,open_time"
+                "size,width,result,gas,proof_size,commit_time,open_time,result_without_root,gas_without_root,proof_size_without_root"
             )?;
             file
         } else {
@@ -66,7 +70,7 @@ async fn main() -> Result<()> {
             println!("Size: {:?}, Width: {:?}", size, width);
 
             // Generate the setup parameters
-            let mut tree = VerkleTree::setup(width.clone(), &mut ark_rng);
+            let mut tree = VerkleTree::setup(width.clone(), &mut ark_rng)?;
 
             // Get setup parameter
             let tau_g2_neg_vec = curve_to_u256_vec(tree.vk.beta_h.into_group().neg().into_affine());
@@ -86,63 +90,50 @@ async fn main() -> Result<()> {
             )
             .await?;
 
-            // Generate vector and commit
-
-            let vec: Vec<Fr> = (1..=size.clone()).map(|x| Fr::from(x as u64)).collect();
+            // Commit the same 32-byte values as merkle_e2e run with the same seed
+            let vec = random_dataset(*size, args.seed);
             let commit_time_start = Instant::now();
-            tree.commit(vec.as_slice());
+            tree.commit_bytes(vec.as_slice())?;
             let commit_time = Instant::now() - commit_time_start;
 
-            // Post root hash to contract
-            let root_hash = scalar_to_u256(tree.root_hash());
-            let builder = contract.commit(root_hash);
+            // Post root commitment to contract, which also sets the root hash
+            let root_vec = curve_to_u256_vec(tree.root_commitment().unwrap().0);
+            let builder = contract.commitRoot(G1Point {
+                X: root_vec[0],
+                Y: root_vec[1],
+            });
             let _ = builder.send().await?.watch().await?;
 
             // Open the tree at a random index and create a proof
             let index = rand::thread_rng().gen_range(0..size.clone());
             let open_time_start = Instant::now();
-            let (value, multi_proof) = tree.open(index).unwrap();
+            let (value, proof) = tree.open(index)?;
             let open_time = Instant::now() - open_time_start;
 
             // Prepare parameters for verification
-            let index = U256::from(index);
-            let value = scalar_to_u256(value);
-            let d_vec = curve_to_u256_vec(multi_proof.multi_proof.d);
-            let pi_vec = curve_to_u256_vec(multi_proof.multi_proof.pi);
-            let coms: Vec<G1Point> = multi_proof
-                .coms
-                .iter()
-                .map(|com| {
-                    let com_vec = curve_to_u256_vec(com.0);
-                    G1Point {
-                        X: com_vec[0],
-                        Y: com_vec[1],
-                    }
-                })
-                .collect();
-            let multi_proof = Multiproof {
-                d: G1Point {
-                    X: d_vec[0],
-                    Y: d_vec[1],
-                },
-                pi: G1Point {
-                    X: pi_vec[0],
-                    Y: pi_vec[1],
-                },
-            };
-            let verkle_proof = VerkleProof {
-                commitments: coms,
-                multiproof: multi_proof,
-            };
+            let (index, value, verkle_proof) = to_verkle_proof(&Opening {
+                index: index as u64,
+                value,
+                proof,
+            });
+            // The same multiproof without the root commitment
+            let mut verkle_proof_without_root = verkle_proof.clone();
+            verkle_proof_without_root.commitments.remove(0);
 
             // Proof size in bytes
             let proof_size = 32 * 4 + 32 * 2 * verkle_proof.commitments.len();
+            let proof_size_without_root =
+                32 * 4 + 32 * 2 * verkle_proof_without_root.commitments.len();
 
             // Verify the proof on the contract
             let builder = contract.verify(index, value, verkle_proof);
             let result = builder.call().await?._0;
             let gas = builder.estimate_gas().await?;
 
+            let builder = contract.verifyWithoutRoot(index, value, verkle_proof_without_root);
+            let result_without_root = builder.call().await?._0;
+            let gas_without_root = builder.estimate_gas().await?;
+
             println!(
                 "Result: {}, Gas: {}, Proof size: {}, Commit time: {}, Open time: {}",
                 result,
@@ -151,16 +142,23 @@ async fn main() -> Result<()> {
                 commit_time.as_millis(),
                 open_time.as_millis()
             );
+            println!(
+                "Without root - Result: {}, Gas: {}, Proof size: {}",
+                result_without_root, gas_without_root, proof_size_without_root
+            );
             writeln!(
                 file,
-                "{},{},{},{},{},{},{}",
+                "{},{},{},{},{},{},{},{},{},{}",
                 size,
                 width,
                 result,
                 gas,
                 proof_size,
                 commit_time.as_millis(),
-                open_time.as_millis()
+                open_time.as_millis(),
+                result_without_root,
+                gas_without_root,
+                proof_size_without_root
             )?;
         }
     }
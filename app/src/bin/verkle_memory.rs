@@ -0,0 +1,142 @@
+use ark_bn254::Fr;
+use ark_std::rand::thread_rng;
+use clap::Parser;
+use eyre::Result;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fs::{self, OpenOptions},
+    io::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use verkle_tree::{store::FileStore, tree::VerkleTree};
+
+// Tracks the current and peak number of heap bytes in use by the process
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(long, value_parser, value_delimiter = ',')]
+    widths: Vec<usize>,
+
+    #[arg(long, value_parser, value_delimiter = ',')]
+    sizes: Vec<usize>,
+
+    #[arg(long)]
+    output: String,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut ark_rng = thread_rng();
+
+    if !std::path::Path::new(&args.output).exists() {
+        fs::create_dir_all(&args.output)?;
+    }
+
+    let file_path = format!("{}/verkle_memory.csv", args.output);
+    let mut file = if !fs::metadata(file_path.clone()).is_ok() {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(file_path)?;
+        writeln!(
+            file,
+            "size,width,tree_bytes,peak_commit_bytes,peak_commit_iter_bytes,peak_file_commit_iter_bytes,file_bytes,file_index_bytes,file_update_bytes"
+        )?;
+        file
+    } else {
+        OpenOptions::new().append(true).open(file_path)?
+    };
+
+    for size in args.sizes.iter() {
+        for width in args.widths.iter() {
+            println!("################################");
+            println!("Size: {:?}, Width: {:?}", size, width);
+
+            let mut tree = VerkleTree::setup(*width, &mut ark_rng)?;
+            let vec: Vec<Fr> = (1..=*size).map(|x| Fr::from(x as u64)).collect();
+
+            // Measure the memory held by the tree and the peak reached while committing,
+            // excluding the setup parameters and the input vector
+            let baseline = ALLOCATED.load(Ordering::Relaxed);
+            PEAK.store(baseline, Ordering::Relaxed);
+            tree.commit(vec.as_slice())?;
+            let tree_bytes = ALLOCATED.load(Ordering::Relaxed) - baseline;
+            let peak_commit_bytes = PEAK.load(Ordering::Relaxed) - baseline;
+
+            drop(vec);
+            drop(tree);
+
+            // Same tree committed from an iterator, without the input vector
+            let mut tree = VerkleTree::setup(*width, &mut ark_rng)?;
+            let baseline = ALLOCATED.load(Ordering::Relaxed);
+            PEAK.store(baseline, Ordering::Relaxed);
+            tree.commit_iter((1..=*size).map(|x| Fr::from(x as u64)))?;
+            let peak_commit_iter_bytes = PEAK.load(Ordering::Relaxed) - baseline;
+            drop(tree);
+
+            // Same iterator committed to a file, where only the index of the nodes stays in memory,
+            // then committed again with one leaf changed, which only appends its path to the file
+            let log_path = format!("{}/verkle_memory.log", args.output);
+            let _ = fs::remove_file(&log_path);
+            let store = FileStore::open(&log_path)?;
+            let mut tree = VerkleTree::setup_with_store(*width, store, &mut ark_rng)?;
+            let baseline = ALLOCATED.load(Ordering::Relaxed);
+            PEAK.store(baseline, Ordering::Relaxed);
+            tree.commit_iter((1..=*size).map(|x| Fr::from(x as u64)))?;
+            let peak_file_commit_iter_bytes = PEAK.load(Ordering::Relaxed) - baseline;
+            let file_bytes = fs::metadata(&log_path)?.len();
+            let file_index_bytes = tree.store.index_bytes();
+            let changed = *size / 2;
+            tree.commit_iter(
+                (1..=*size).map(|x| Fr::from(if x == changed { 0 } else { x as u64 })),
+            )?;
+            let file_update_bytes = fs::metadata(&log_path)?.len() - file_bytes;
+            drop(tree);
+            fs::remove_file(&log_path)?;
+
+            println!(
+                "Tree size: {} bytes, Peak commit memory: {} bytes, Peak commit_iter memory: {} bytes, Peak file commit_iter memory: {} bytes, File size: {} bytes, File index: {} bytes, File update: {} bytes",
+                tree_bytes, peak_commit_bytes, peak_commit_iter_bytes, peak_file_commit_iter_bytes, file_bytes, file_index_bytes, file_update_bytes
+            );
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{},{}",
+                size,
+                width,
+                tree_bytes,
+                peak_commit_bytes,
+                peak_commit_iter_bytes,
+                peak_file_commit_iter_bytes,
+                file_bytes,
+                file_index_bytes,
+                file_update_bytes
+            )?;
+        }
+    }
+
+    Ok(())
+}
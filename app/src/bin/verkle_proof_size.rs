@@ -0,0 +1,101 @@
+use ark_bn254::Fr;
+use ark_std::rand::thread_rng;
+use clap::Parser;
+use eyre::Result;
+use rand::Rng;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+};
+use verkle_tree::{compressed::CompressedVerkleTree, sparse::SparseVerkleTree};
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(long, value_parser, value_delimiter = ',')]
+    widths: Vec<usize>,
+
+    #[arg(long)]
+    height: usize,
+
+    #[arg(long, value_parser, value_delimiter = ',')]
+    sizes: Vec<usize>,
+
+    #[arg(long)]
+    output: String,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut ark_rng = thread_rng();
+
+    if !std::path::Path::new(&args.output).exists() {
+        fs::create_dir_all(&args.output)?;
+    }
+
+    let file_path = format!("{}/verkle_proof_size.csv", args.output);
+    let mut file = if !fs::metadata(file_path.clone()).is_ok() {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(file_path)?;
+        writeln!(
+            file,
+            "size,width,height,sparse_proof_size,compressed_proof_size"
+        )?;
+        file
+    } else {
+        OpenOptions::new().append(true).open(file_path)?
+    };
+
+    for size in args.sizes.iter() {
+        for width in args.widths.iter() {
+            println!("################################");
+            println!(
+                "Size: {:?}, Width: {:?}, Height: {:?}",
+                size, width, args.height
+            );
+
+            let mut sparse_tree = SparseVerkleTree::setup(*width, args.height, &mut ark_rng)?;
+            let mut compressed_tree =
+                CompressedVerkleTree::setup(*width, args.height, &mut ark_rng)?;
+
+            // Populate random indices of the whole index space
+            let capacity = width.checked_pow(args.height as u32).unwrap_or(usize::MAX);
+            let entries: Vec<(usize, Fr)> = (1..=*size)
+                .map(|x| {
+                    (
+                        rand::thread_rng().gen_range(0..capacity),
+                        Fr::from(x as u64),
+                    )
+                })
+                .collect();
+            sparse_tree.commit(entries.as_slice())?;
+            compressed_tree.commit(entries.as_slice())?;
+
+            // Average proof size in bytes over every populated index
+            let mut sparse_proof_size = 0;
+            let mut compressed_proof_size = 0;
+            for &(index, _) in entries.iter() {
+                let (_, sparse_proof) = sparse_tree.open(index)?;
+                let (_, compressed_proof) = compressed_tree.open(index)?;
+                sparse_proof_size += 32 * 4 + 32 * 2 * sparse_proof.coms.len();
+                compressed_proof_size += 32 * 4 + 32 * 2 * compressed_proof.coms.len();
+            }
+            let sparse_proof_size = sparse_proof_size as f64 / entries.len() as f64;
+            let compressed_proof_size = compressed_proof_size as f64 / entries.len() as f64;
+
+            println!(
+                "Sparse proof size: {}, Compressed proof size: {}",
+                sparse_proof_size, compressed_proof_size
+            );
+            writeln!(
+                file,
+                "{},{},{},{},{}",
+                size, width, args.height, sparse_proof_size, compressed_proof_size
+            )?;
+        }
+    }
+
+    Ok(())
+}
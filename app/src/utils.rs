@@ -1,8 +1,14 @@
 use alloy::primitives::U256;
-use ark_bn254::{Fq, Fr};
+use ark_bn254::{Bn254, Fq, Fr};
 use ark_ff::ToConstraintField;
 use ark_ff::{BigInteger, PrimeField};
-use rand::Rng;
+use foundry_contracts::verkleverifier::{
+    Curve::G1Point,
+    VerkleVerifier::{Multiproof, VerkleProof},
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use verkle_tree::data_structures::Opening;
 
 pub fn curve_to_u256_vec<T: ToConstraintField<Fq>>(input: T) -> Vec<U256> {
     input
@@ -17,11 +23,41 @@ pub fn scalar_to_u256(input: Fr) -> U256 {
     U256::from_be_slice(&input.into_bigint().to_bytes_be())
 }
 
+/// Returns the index, value and proof of `opening` as passed to `VerkleVerifier.verify`, read
+/// from the words of its EVM encoding.
+pub fn to_verkle_proof(opening: &Opening<Bn254>) -> (U256, U256, VerkleProof) {
+    let words: Vec<U256> = opening
+        .to_evm_bytes()
+        .chunks(32)
+        .map(U256::from_be_slice)
+        .collect();
+    let point = |i: usize| G1Point {
+        X: words[i],
+        Y: words[i + 1],
+    };
+    let n = opening.proof.coms.len();
+    let verkle_proof = VerkleProof {
+        commitments: (0..n).map(|i| point(3 + 2 * i)).collect(),
+        multiproof: Multiproof {
+            d: point(3 + 2 * n),
+            pi: point(5 + 2 * n),
+        },
+    };
+    (words[0], words[1], verkle_proof)
+}
+
 pub fn random_bytes32() -> Vec<u8> {
     let mut rng = rand::thread_rng();
     (0..32).map(|_| rng.gen()).collect()
 }
 
+/// Random 32-byte values drawn from `seed`, so that the Merkle and verkle benchmarks commit the
+/// same dataset when run with the same seed.
+pub fn random_dataset(size: usize, seed: u64) -> Vec<Vec<u8>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..size).map(|_| rng.gen::<[u8; 32]>().to_vec()).collect()
+}
+
 pub fn to_bytes32_array(value: &Vec<u8>) -> [u8; 32] {
     let mut array = [0u8; 32];
     let bytes = &value[..array.len()];
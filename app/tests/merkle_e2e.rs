@@ -1,10 +1,17 @@
 use alloy::primitives::{FixedBytes, U256};
 use alloy::providers::builder;
+use app::utils::*;
 use eyre::Result;
-use foundry_contracts::merkleverifier::MerkleVerifier;
-use merkle_tree::tree::MerkleTree;
+use foundry_contracts::{
+    merkleverifier::MerkleVerifier, prefixedmerkleverifier::PrefixedMerkleVerifier,
+    sha256merkleverifier::Sha256MerkleVerifier,
+};
+use merkle_tree::{
+    hasher::Sha256,
+    tree::{HashDomain, MerkleTree, Padding},
+    verifier::{verify_proof, TreeSize},
+};
 use rand::Rng;
-use app::utils::*;
 
 #[tokio::test]
 async fn test_merkle_e2e() -> Result<()> {
@@ -26,7 +33,7 @@ async fn test_merkle_e2e() -> Result<()> {
     let vec = vec.iter().collect::<Vec<_>>();
 
     // Commit
-    tree.commit(vec.as_slice());
+    tree.commit(vec.as_slice())?;
     let com = tree.root_hash().unwrap();
     let builder = contract.commit(FixedBytes::from_slice(&com));
     let _ = builder.send().await?.watch().await?;
@@ -37,7 +44,15 @@ async fn test_merkle_e2e() -> Result<()> {
 
     // Open and generate proof
     let random_index = rand::thread_rng().gen_range(0..vec_length);
-    let (value, proof) = tree.open(random_index);
+    let (value, proof) = tree.open(random_index)?;
+    let local_result = verify_proof(
+        &com,
+        branching_factor,
+        TreeSize::LeafCount(vec_length),
+        random_index,
+        &value,
+        &proof,
+    );
     let proof = proof
         .hashes
         .iter()
@@ -54,6 +69,100 @@ async fn test_merkle_e2e() -> Result<()> {
     let gas = builder.estimate_gas().await?;
     println!("Gas used: {}", gas);
 
+    assert!(result);
+    assert_eq!(result, local_result);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_merkle_e2e_domain_separated() -> Result<()> {
+    let provider = builder().with_recommended_fillers().on_anvil_with_wallet();
+
+    let branching_factor = 3;
+    let mut tree = MerkleTree::setup_with_domain(branching_factor, HashDomain::SEPARATED);
+
+    // Deploy the contract with the same leaf and node prefixes
+    let contract = PrefixedMerkleVerifier::deploy(
+        &provider,
+        U256::from(branching_factor),
+        FixedBytes::from([0x00]),
+        FixedBytes::from([0x01]),
+    )
+    .await?;
+
+    let vec_length = 100;
+    let vec = (0..vec_length)
+        .map(|_| random_bytes32())
+        .collect::<Vec<_>>();
+    let vec = vec.iter().collect::<Vec<_>>();
+
+    tree.commit(vec.as_slice())?;
+    let com = tree.root_hash().unwrap();
+    let builder = contract.commit(FixedBytes::from_slice(&com));
+    let _ = builder.send().await?.watch().await?;
+
+    let random_index = rand::thread_rng().gen_range(0..vec_length);
+    let (value, proof) = tree.open(random_index)?;
+    let proof = proof
+        .hashes
+        .iter()
+        .map(|p| FixedBytes::from_slice(p))
+        .collect::<Vec<_>>();
+
+    let builder = contract.verify(
+        U256::from(random_index),
+        U256::from_be_bytes(to_bytes32_array(&value)),
+        proof,
+    );
+    let result = builder.call().await?._0;
+    let gas = builder.estimate_gas().await?;
+    println!("Gas used: {}", gas);
+
+    assert!(result);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_merkle_e2e_sha256() -> Result<()> {
+    let provider = builder().with_recommended_fillers().on_anvil_with_wallet();
+
+    let branching_factor = 3;
+    let mut tree: MerkleTree<Sha256> =
+        MerkleTree::setup_with_hasher(branching_factor, HashDomain::Plain, Padding::RepeatLast)?;
+
+    // Deploy the contract hashing with the SHA-256 precompile
+    let contract = Sha256MerkleVerifier::deploy(&provider, U256::from(branching_factor)).await?;
+
+    let vec_length = 100;
+    let vec = (0..vec_length)
+        .map(|_| random_bytes32())
+        .collect::<Vec<_>>();
+    let vec = vec.iter().collect::<Vec<_>>();
+
+    tree.commit(vec.as_slice())?;
+    let com = tree.root_hash().unwrap();
+    let builder = contract.commit(FixedBytes::from_slice(&com));
+    let _ = builder.send().await?.watch().await?;
+
+    let random_index = rand::thread_rng().gen_range(0..vec_length);
+    let (value, proof) = tree.open(random_index)?;
+    let proof = proof
+        .hashes
+        .iter()
+        .map(|p| FixedBytes::from_slice(p))
+        .collect::<Vec<_>>();
+
+    let builder = contract.verify(
+        U256::from(random_index),
+        U256::from_be_bytes(to_bytes32_array(&value)),
+        proof,
+    );
+    let result = builder.call().await?._0;
+    let gas = builder.estimate_gas().await?;
+    println!("Gas used: {}", gas);
+
     assert!(result);
 
     Ok(())
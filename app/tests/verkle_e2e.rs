@@ -1,6 +1,6 @@
 use alloy::primitives::U256;
 use alloy::providers::builder;
-use app::utils::{curve_to_u256_vec, scalar_to_u256};
+use app::utils::{curve_to_u256_vec, scalar_to_u256, to_verkle_proof};
 use ark_bn254::Fr;
 use ark_ec::{AffineRepr, CurveGroup};
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
@@ -8,11 +8,14 @@ use ark_std::rand::thread_rng;
 use eyre::Result;
 use foundry_contracts::verkleverifier::{
     Curve::{G1Point, G2Point},
-    VerkleVerifier::{self, Multiproof, VerkleProof},
+    VerkleVerifier,
 };
 use std::ops::Neg;
 
-use verkle_tree::tree::VerkleTree;
+use verkle_tree::{
+    data_structures::{Opening, ProofMode},
+    tree::VerkleTree,
+};
 type Domain = GeneralEvaluationDomain<Fr>;
 
 #[tokio::test]
@@ -24,7 +27,7 @@ async fn test_verkle_e2e() -> Result<()> {
     let width = 4;
 
     // Generate the setup parameters
-    let mut tree = VerkleTree::setup(width, &mut rng);
+    let mut tree = VerkleTree::setup(width, &mut rng)?;
 
     // Get setup parameter
     let tau_g2_neg_vec = curve_to_u256_vec(tree.vk.beta_h.into_group().neg().into_affine());
@@ -47,47 +50,23 @@ async fn test_verkle_e2e() -> Result<()> {
 
     // Generate vector and commit
     let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
-    tree.commit(vec.as_slice());
+    tree.commit(vec.as_slice())?;
 
     // Post root hash to contract
-    let root_hash = scalar_to_u256(tree.root_hash());
+    let root_hash = scalar_to_u256(tree.root_hash()?);
     let builder = contract.commit(root_hash);
     let _ = builder.send().await?.watch().await?;
 
     // Open the tree at a random index and create a proof
     let index = 2;
-    let (value, multi_proof) = tree.open(index).unwrap();
+    let (value, proof) = tree.open(index)?;
 
     // Prepare parameters for verification
-    let index = U256::from(index);
-    let value = scalar_to_u256(value);
-    let d_vec = curve_to_u256_vec(multi_proof.multi_proof.d);
-    let pi_vec = curve_to_u256_vec(multi_proof.multi_proof.pi);
-    let coms: Vec<G1Point> = multi_proof
-        .coms
-        .iter()
-        .map(|com| {
-            let com_vec = curve_to_u256_vec(com.0);
-            G1Point {
-                X: com_vec[0],
-                Y: com_vec[1],
-            }
-        })
-        .collect();
-    let multi_proof = Multiproof {
-        d: G1Point {
-            X: d_vec[0],
-            Y: d_vec[1],
-        },
-        pi: G1Point {
-            X: pi_vec[0],
-            Y: pi_vec[1],
-        },
-    };
-    let verkle_proof = VerkleProof {
-        commitments: coms,
-        multiproof: multi_proof,
-    };
+    let (index, value, verkle_proof) = to_verkle_proof(&Opening {
+        index: index as u64,
+        value,
+        proof,
+    });
 
     // Verify the proof on the contract
     let builder = contract.verify(index, value, verkle_proof);
@@ -99,3 +78,118 @@ async fn test_verkle_e2e() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_verkle_e2e_without_root() -> Result<()> {
+    let provider = builder().with_recommended_fillers().on_anvil_with_wallet();
+    let mut rng = thread_rng();
+
+    let n = 16;
+    let width = 4;
+    let mut tree = VerkleTree::setup(width, &mut rng)?;
+
+    let tau_g2_neg_vec = curve_to_u256_vec(tree.vk.beta_h.into_group().neg().into_affine());
+    let tau_g2_neg_point = G2Point {
+        X: [tau_g2_neg_vec[1], tau_g2_neg_vec[0]],
+        Y: [tau_g2_neg_vec[3], tau_g2_neg_vec[2]],
+    };
+    let domain = Domain::new(width).unwrap();
+    let roots_of_unity = scalar_to_u256(domain.element(1));
+    let contract = VerkleVerifier::deploy(
+        &provider,
+        tau_g2_neg_point,
+        U256::from(width),
+        roots_of_unity,
+    )
+    .await?;
+
+    let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+    tree.commit(vec.as_slice())?;
+
+    // Post the root commitment instead of its hash
+    let root_vec = curve_to_u256_vec(tree.root_commitment().unwrap().0);
+    let builder = contract.commitRoot(G1Point {
+        X: root_vec[0],
+        Y: root_vec[1],
+    });
+    let _ = builder.send().await?.watch().await?;
+    assert_eq!(
+        contract.rootHash().call().await?._0,
+        scalar_to_u256(tree.root_hash()?)
+    );
+
+    let index = 2;
+    let (value, proof) = tree.open_with_mode(index, ProofMode::OmitRoot)?;
+    let (index, value, verkle_proof) = to_verkle_proof(&Opening {
+        index: index as u64,
+        value,
+        proof,
+    });
+
+    let builder = contract.verifyWithoutRoot(index, value, verkle_proof.clone());
+    let result = builder.call().await?._0;
+    let gas = builder.estimate_gas().await?;
+    println!("Gas used without root: {}", gas);
+
+    assert!(result);
+
+    // Replacing the root by its hash drops the stored commitment, so the proof no longer verifies
+    let builder = contract.commit(scalar_to_u256(Fr::from(1)));
+    let _ = builder.send().await?.watch().await?;
+    let result = contract
+        .verifyWithoutRoot(index, value, verkle_proof)
+        .call()
+        .await?
+        ._0;
+    assert!(!result);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verkle_e2e_non_power_of_two_width() -> Result<()> {
+    let provider = builder().with_recommended_fillers().on_anvil_with_wallet();
+    let mut rng = thread_rng();
+
+    let n = 150;
+    let width = 10;
+    let mut tree = VerkleTree::setup(width, &mut rng)?;
+
+    let tau_g2_neg_vec = curve_to_u256_vec(tree.vk.beta_h.into_group().neg().into_affine());
+    let tau_g2_neg_point = G2Point {
+        X: [tau_g2_neg_vec[1], tau_g2_neg_vec[0]],
+        Y: [tau_g2_neg_vec[3], tau_g2_neg_vec[2]],
+    };
+    let domain = Domain::new(width).unwrap();
+    let roots_of_unity = scalar_to_u256(domain.element(1));
+    let contract = VerkleVerifier::deploy(
+        &provider,
+        tau_g2_neg_point,
+        U256::from(width),
+        roots_of_unity,
+    )
+    .await?;
+
+    let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+    tree.commit(vec.as_slice())?;
+
+    let root_hash = scalar_to_u256(tree.root_hash()?);
+    let builder = contract.commit(root_hash);
+    let _ = builder.send().await?.watch().await?;
+
+    // Indices whose path uses the last child of a node
+    for index in [9, 99, 149] {
+        let (value, proof) = tree.open(index)?;
+        let (index, value, verkle_proof) = to_verkle_proof(&Opening {
+            index: index as u64,
+            value,
+            proof,
+        });
+
+        let builder = contract.verify(index, value, verkle_proof);
+        let result = builder.call().await?._0;
+        assert!(result);
+    }
+
+    Ok(())
+}
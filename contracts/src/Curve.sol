@@ -155,4 +155,14 @@ library Curve {
     function hash(G1Point calldata p) internal pure returns (Field.Fr) {
         return Field.hashBytes(toBytes(p));
     }
+
+    function toBytesMemory(
+        G1Point memory p
+    ) internal pure returns (bytes memory) {
+        return abi.encodePacked(p.X, p.Y);
+    }
+
+    function hashMemory(G1Point memory p) internal pure returns (Field.Fr) {
+        return Field.hashBytes(toBytesMemory(p));
+    }
 }
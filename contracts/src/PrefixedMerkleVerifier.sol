@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.0;
+
+/// @notice `MerkleVerifier` for trees hashed with `HashDomain::Prefixed`, where leaves are hashed
+/// as keccak256(leafPrefix || value) and internal nodes as keccak256(nodePrefix || children).
+contract PrefixedMerkleVerifier {
+    bytes32 public rootHash;
+    uint256 internal immutable treeWidth;
+    bytes1 internal immutable leafPrefix;
+    bytes1 internal immutable nodePrefix;
+
+    constructor(uint256 treeWidth_, bytes1 leafPrefix_, bytes1 nodePrefix_) {
+        treeWidth = treeWidth_;
+        leafPrefix = leafPrefix_;
+        nodePrefix = nodePrefix_;
+    }
+
+    function commit(bytes32 newRootHash) external {
+        rootHash = newRootHash;
+    }
+
+    function verify(
+        uint256 index,
+        uint256 value,
+        bytes32[] memory proof
+    ) external view returns (bool) {
+        bytes32 computedHash = keccak256(abi.encodePacked(leafPrefix, value));
+
+        for (uint256 proofIdx = 0; proofIdx < proof.length; ) {
+            bytes memory siblings = abi.encodePacked(nodePrefix);
+            for (
+                uint256 siblingsIdx = 0;
+                siblingsIdx < treeWidth;
+                siblingsIdx++
+            ) {
+                siblings = abi.encodePacked(
+                    siblings,
+                    siblingsIdx == index % treeWidth
+                        ? computedHash
+                        : proof[proofIdx++]
+                );
+            }
+            computedHash = keccak256(siblings);
+            index /= treeWidth;
+        }
+
+        return computedHash == rootHash;
+    }
+}
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.0;
+
+/// @notice `MerkleVerifier` for trees hashed with `Sha256`, which goes through the SHA-256
+/// precompile instead of the KECCAK256 opcode.
+contract Sha256MerkleVerifier {
+    bytes32 public rootHash;
+    uint256 internal immutable treeWidth;
+
+    constructor(uint256 treeWidth_) {
+        treeWidth = treeWidth_;
+    }
+
+    function commit(bytes32 newRootHash) external {
+        rootHash = newRootHash;
+    }
+
+    function verify(
+        uint256 index,
+        uint256 value,
+        bytes32[] memory proof
+    ) external view returns (bool) {
+        bytes32 computedHash = sha256(abi.encodePacked(value));
+
+        for (uint256 proofIdx = 0; proofIdx < proof.length; ) {
+            bytes memory siblings;
+            for (
+                uint256 siblingsIdx = 0;
+                siblingsIdx < treeWidth;
+                siblingsIdx++
+            ) {
+                siblings = abi.encodePacked(
+                    siblings,
+                    siblingsIdx == index % treeWidth
+                        ? computedHash
+                        : proof[proofIdx++]
+                );
+            }
+            computedHash = sha256(siblings);
+            index /= treeWidth;
+        }
+
+        return computedHash == rootHash;
+    }
+}
@@ -23,6 +23,9 @@ contract VerkleVerifier {
     // Hash of the commitment of the tree root
     Field.Fr public rootHash;
 
+    // Commitment of the tree root, only set by `commitRoot`
+    Curve.G1Point public rootCommitment;
+
     // Proof of KZG Multiproof
     struct Multiproof {
         Curve.G1Point d;
@@ -51,6 +54,13 @@ contract VerkleVerifier {
 
     function commit(Field.Fr rootHash_) external {
         rootHash = rootHash_;
+        // The stored commitment belongs to the previous root
+        delete rootCommitment;
+    }
+
+    function commitRoot(Curve.G1Point calldata rootCommitment_) external {
+        rootCommitment = rootCommitment_;
+        rootHash = rootCommitment_.hash();
     }
 
     function verify(
@@ -81,6 +91,45 @@ contract VerkleVerifier {
             rootHash.eq(verkleProof.commitments[0].hash());
     }
 
+    // Verifies a proof whose commitments start below the root. The root
+    // commitment stored by `commitRoot` is used in place of the first one,
+    // so the commitments are copied to memory.
+    function verifyWithoutRoot(
+        uint256 index,
+        Field.Fr value,
+        VerkleProof calldata verkleProof
+    ) external view returns (bool) {
+        Curve.G1Point memory root = rootCommitment;
+        if (!rootHash.eq(root.hashMemory())) {
+            return false;
+        }
+
+        Curve.G1Point[] memory coms = new Curve.G1Point[](
+            verkleProof.commitments.length + 1
+        );
+        coms[0] = root;
+        for (uint256 i = 0; i < verkleProof.commitments.length; i++) {
+            coms[i + 1] = verkleProof.commitments[i];
+        }
+
+        Field.Fr[] memory pathIndex = computePathIndex(index, coms.length);
+        (Field.Fr r, Field.Fr t, Field.Fr[] memory pathValues) = computeRTValuesMemory(
+            coms,
+            pathIndex,
+            value,
+            verkleProof.multiproof.d
+        );
+        (Curve.G1Point memory e, Field.Fr y) = computeEYMemory(
+            r,
+            t,
+            pathValues,
+            pathIndex,
+            coms
+        );
+
+        return checkPairing(t, y, e, verkleProof.multiproof);
+    }
+
     function computePathIndex(
         uint256 index,
         uint256 height
@@ -129,6 +178,40 @@ contract VerkleVerifier {
         }
     }
 
+    function computeRTValuesMemory(
+        Curve.G1Point[] memory coms,
+        Field.Fr[] memory pathIndex,
+        Field.Fr value,
+        Curve.G1Point calldata d
+    ) internal pure returns (Field.Fr r, Field.Fr t, Field.Fr[] memory pathValues) {
+        bytes memory input;
+        pathValues = new Field.Fr[](coms.length);
+        for (uint256 i = 0; i < coms.length; i++) {
+            pathValues[i] = i < coms.length - 1 ? coms[i + 1].hashMemory() : value.hash();
+            input = abi.encodePacked(input, coms[i].toBytesMemory());
+            input = abi.encodePacked(input, pathIndex[i]);
+            input = abi.encodePacked(input, pathValues[i]);
+        }
+        r = Field.hashBytes(input);
+        t = Field.hashBytes(abi.encodePacked(d.toBytes(), r));
+    }
+
+    function computeEYMemory(
+        Field.Fr r,
+        Field.Fr t,
+        Field.Fr[] memory pathValues,
+        Field.Fr[] memory pathIndex,
+        Curve.G1Point[] memory coms
+    ) internal view returns (Curve.G1Point memory e, Field.Fr y) {
+        Field.Fr rExp = Field.get(1);
+        for (uint256 i = 0; i < coms.length; i++) {
+            Field.Fr divisor = t.sub(pathIndex[i]);
+            y = y.add(rExp.mul(pathValues[i].div(divisor)));
+            e = e.add(coms[i].mulScalar(rExp.div(divisor)));
+            rExp = rExp.mul(r);
+        }
+    }
+
     function tauG2neg() internal view returns (Curve.G2Point memory) {
         return Curve.G2Point([tauG2neg_x0, tauG2neg_x1], [tauG2neg_y0, tauG2neg_y1]);
     }
@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.24;
+
+import "forge-std/Test.sol";
+import {MerkleVerifier} from "src/MerkleVerifier.sol";
+import {PrefixedMerkleVerifier} from "src/PrefixedMerkleVerifier.sol";
+
+contract PrefixedMerkleVerifierTest is Test {
+    bytes1 constant LEAF_PREFIX = 0x00;
+    bytes1 constant NODE_PREFIX = 0x01;
+
+    function leafHash(uint256 value) internal pure returns (bytes32) {
+        return keccak256(abi.encodePacked(LEAF_PREFIX, value));
+    }
+
+    function nodeHash(bytes32 left, bytes32 right) internal pure returns (bytes32) {
+        return keccak256(abi.encodePacked(NODE_PREFIX, left, right));
+    }
+
+    function test_verify() public {
+        PrefixedMerkleVerifier verifier = new PrefixedMerkleVerifier(2, LEAF_PREFIX, NODE_PREFIX);
+        bytes32 left = nodeHash(leafHash(1), leafHash(2));
+        bytes32 right = nodeHash(leafHash(3), leafHash(4));
+        verifier.commit(nodeHash(left, right));
+
+        bytes32[] memory proof = new bytes32[](2);
+        proof[0] = leafHash(3);
+        proof[1] = left;
+        assertTrue(verifier.verify(3, 4, proof));
+        assertFalse(verifier.verify(3, 5, proof));
+        assertFalse(verifier.verify(2, 4, proof));
+    }
+
+    function test_rejectInternalNodeAsLeaf() public {
+        PrefixedMerkleVerifier verifier = new PrefixedMerkleVerifier(2, LEAF_PREFIX, NODE_PREFIX);
+        bytes32 left = nodeHash(leafHash(1), leafHash(2));
+        bytes32 right = nodeHash(leafHash(3), leafHash(4));
+        verifier.commit(nodeHash(left, right));
+
+        // An internal node hash passed off as a leaf one level up
+        bytes32[] memory proof = new bytes32[](1);
+        proof[0] = right;
+        assertFalse(verifier.verify(0, uint256(left), proof));
+    }
+
+    function test_rejectPlainTree() public {
+        PrefixedMerkleVerifier verifier = new PrefixedMerkleVerifier(2, LEAF_PREFIX, NODE_PREFIX);
+        MerkleVerifier plainVerifier = new MerkleVerifier(2);
+        bytes32 left = keccak256(
+            abi.encodePacked(keccak256(abi.encodePacked(uint256(1))), keccak256(abi.encodePacked(uint256(2))))
+        );
+        verifier.commit(left);
+        plainVerifier.commit(left);
+
+        bytes32[] memory proof = new bytes32[](1);
+        proof[0] = keccak256(abi.encodePacked(uint256(2)));
+        assertTrue(plainVerifier.verify(0, 1, proof));
+        assertFalse(verifier.verify(0, 1, proof));
+    }
+}
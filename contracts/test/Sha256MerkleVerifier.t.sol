@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.24;
+
+import "forge-std/Test.sol";
+import {Sha256MerkleVerifier} from "src/Sha256MerkleVerifier.sol";
+
+contract Sha256MerkleVerifierTest is Test {
+    function leafHash(uint256 value) internal pure returns (bytes32) {
+        return sha256(abi.encodePacked(value));
+    }
+
+    function nodeHash(bytes32 left, bytes32 middle, bytes32 right) internal pure returns (bytes32) {
+        return sha256(abi.encodePacked(left, middle, right));
+    }
+
+    function test_verify() public {
+        Sha256MerkleVerifier verifier = new Sha256MerkleVerifier(3);
+        bytes32 first = nodeHash(leafHash(1), leafHash(2), leafHash(3));
+        bytes32 second = nodeHash(leafHash(4), leafHash(5), leafHash(5));
+        verifier.commit(nodeHash(first, second, second));
+
+        bytes32[] memory proof = new bytes32[](4);
+        proof[0] = leafHash(4);
+        proof[1] = leafHash(5);
+        proof[2] = first;
+        proof[3] = second;
+        assertTrue(verifier.verify(5, 5, proof));
+        assertFalse(verifier.verify(5, 4, proof));
+        assertFalse(verifier.verify(3, 5, proof));
+    }
+
+    function test_rejectKeccakTree() public {
+        Sha256MerkleVerifier verifier = new Sha256MerkleVerifier(2);
+        bytes32 left = keccak256(abi.encodePacked(uint256(1)));
+        bytes32 right = keccak256(abi.encodePacked(uint256(2)));
+        verifier.commit(keccak256(abi.encodePacked(left, right)));
+
+        bytes32[] memory proof = new bytes32[](1);
+        proof[0] = right;
+        assertFalse(verifier.verify(0, 1, proof));
+    }
+}
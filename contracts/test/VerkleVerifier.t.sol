@@ -75,6 +75,32 @@ contract VerkleVerifierTest is Test {
         );
     }
 
+    function test_computePathIndexNonPowerOfTwoWidth() public {
+        // Width 10 is interpolated over the 16th roots of unity, the same points as the
+        // `GeneralEvaluationDomain` of the Rust tree
+        uint256 width = 10;
+        uint256 height = 2;
+        uint256 index = 19;
+        Curve.G2Point memory zero;
+        Verkle verkle = new Verkle(
+            zero,
+            width,
+            Field.get(
+                14940766826517323942636479241147756311199852622225275649687664389641784935947
+            )
+        );
+
+        Field.Fr[] memory path = verkle.exposed_computePathIndex(index, height);
+        assertEq(
+            Field.Fr.unwrap(path[0]),
+            14940766826517323942636479241147756311199852622225275649687664389641784935947
+        );
+        assertEq(
+            Field.Fr.unwrap(path[1]),
+            6947476045321951279609926504109518777348511778190758694010539796934023559670
+        );
+    }
+
     function test_hash() public pure {
         Field.Fr a = Field.get(1);
         Field.Fr b = Field.get(2);
@@ -8,6 +8,9 @@ use ark_poly_commit::{
 };
 use ark_std::{ops::Div, ops::Mul, rand::RngCore, One, Zero};
 
+type CommitResult<E, P> =
+    Result<(Commitment<E>, Randomness<<E as Pairing>::ScalarField, P>), Error>;
+
 pub struct KZG<E: Pairing, P: DenseUVPolynomial<E::ScalarField>> {
     _engine: PhantomData<E>,
     _poly: PhantomData<P>,
@@ -49,7 +52,7 @@ where
 
         let neg_powers_of_h = if produce_g2_powers {
             let mut neg_powers_of_beta = vec![E::ScalarField::one()];
-            let mut cur = E::ScalarField::one() / &beta;
+            let mut cur = E::ScalarField::one() / beta;
             for _ in 0..max_degree {
                 neg_powers_of_beta.push(cur);
                 cur /= &beta;
@@ -107,11 +110,11 @@ where
         polynomial: &P,
         hiding_bound: Option<usize>,
         rng: Option<&mut dyn RngCore>,
-    ) -> Result<(Commitment<E>, Randomness<E::ScalarField, P>), Error> {
+    ) -> CommitResult<E, P> {
         kzg10::KZG10::commit(powers, polynomial, hiding_bound, rng)
     }
 
-    pub fn open<'a>(
+    pub fn open(
         powers: &Powers<E>,
         p: &P,
         point: P::Point,
@@ -41,7 +41,7 @@ where
         let eval_polys = Self::compute_eval_polys(points_slice, values_slice);
         let zero_polys = Self::compute_zero_polys(points_slice);
 
-        let r = Self::compute_r(coms);
+        let r = Self::compute_r(coms, points_slice, values_slice);
         let g_poly = Self::compute_witness_poly(polys, &eval_polys, &zero_polys, &r);
         let (d_com, _) = KZG::<E, P>::commit(powers, &g_poly, None, None)?;
         let t = Self::compute_t(&d_com, &r);
@@ -62,7 +62,7 @@ where
         let eval_polys = Self::compute_eval_polys(points_slice, values_slice);
         let zero_polys = Self::compute_zero_polys(points_slice);
 
-        let r = Self::compute_r(coms);
+        let r = Self::compute_r(coms, points_slice, values_slice);
         let t = Self::compute_t(&proof.d, &r);
         let (e, y) = Self::compute_e_y(coms, &eval_polys, &zero_polys, &r, &t);
         let result = Self::check_pairing(vk, &e, &proof.d, &y, &proof.pi, &t);
@@ -109,13 +109,17 @@ where
 
         points_slice
             .iter()
-            .map(|points| compute_zero_poly(&points))
+            .map(|points| compute_zero_poly(points))
             .collect()
     }
 
-    fn compute_r(coms: &[Commitment<E>]) -> E::ScalarField {
+    fn compute_r(
+        coms: &[Commitment<E>],
+        points_slice: &[&[P::Point]],
+        values_slice: &[&[E::ScalarField]],
+    ) -> E::ScalarField {
         let mut hasher = D::new();
-        for com in coms {
+        for (com, points, values) in izip!(coms, points_slice, values_slice) {
             // Append com
             let field_elements = com.to_field_elements().unwrap();
             for element in field_elements {
@@ -123,7 +127,14 @@ where
                     continue;
                 }
                 let bytes: Vec<u8> = element.into_bigint().to_bytes_be();
-                hasher.update(&bytes.as_slice());
+                hasher.update(bytes);
+            }
+
+            // Append points and values, so that values of polys opened at the same points
+            // cannot be traded against each other
+            for (point, value) in izip!(points.iter(), values.iter()) {
+                hasher.update(point.into_bigint().to_bytes_be());
+                hasher.update(value.into_bigint().to_bytes_be());
             }
         }
 
@@ -140,12 +151,12 @@ where
                 continue;
             }
             let bytes: Vec<u8> = element.into_bigint().to_bytes_be();
-            hasher.update(&bytes.as_slice());
+            hasher.update(bytes);
         }
 
         // Append point
         let bytes = r.into_bigint().to_bytes_be();
-        hasher.update(&bytes.as_slice());
+        hasher.update(bytes);
 
         // Calculate hash
         let result = hasher.finalize();
@@ -160,9 +171,9 @@ where
     ) -> P {
         let mut g_poly = P::zero();
         for (j, (poly, eval_poly, zero_poly)) in izip!(polys, eval_polys, zero_polys).enumerate() {
-            let r_j = P::from_coefficients_slice(&[r.pow(&[j as u64])]);
-            let dividend = poly.sub(&eval_poly).mul(&r_j);
-            let g_frac = dividend.div(&zero_poly);
+            let r_j = P::from_coefficients_slice(&[r.pow([j as u64])]);
+            let dividend = poly.sub(eval_poly).mul(&r_j);
+            let g_frac = dividend.div(zero_poly);
             g_poly = g_poly.add(g_frac)
         }
 
@@ -180,9 +191,9 @@ where
         let mut h_poly = P::zero();
 
         for (j, (poly, eval_poly, zero_poly)) in izip!(polys, eval_polys, zero_polys).enumerate() {
-            let r_j = r.pow(&[j as u64]);
-            let divisor: E::ScalarField = zero_poly.evaluate(&t);
-            let y_frac = r_j.mul(&eval_poly.evaluate(&t)).div(&divisor);
+            let r_j = r.pow([j as u64]);
+            let divisor: E::ScalarField = zero_poly.evaluate(t);
+            let y_frac = r_j.mul(&eval_poly.evaluate(t)).div(&divisor);
             y = y.add(y_frac);
 
             let r_j = P::from_coefficients_slice(&[r_j]);
@@ -204,9 +215,9 @@ where
         let mut e_scalars = Vec::new();
         let mut y = E::ScalarField::zero();
         for (j, (eval_poly, zero_poly)) in izip!(eval_polys, zero_polys).enumerate() {
-            let r_j = r.pow(&[j as u64]);
-            let divisor: E::ScalarField = zero_poly.evaluate(&t);
-            let y_frac = r_j.mul(&eval_poly.evaluate(&t)).div(&divisor);
+            let r_j = r.pow([j as u64]);
+            let divisor: E::ScalarField = zero_poly.evaluate(t);
+            let y_frac = r_j.mul(&eval_poly.evaluate(t)).div(&divisor);
             y = y.add(y_frac);
 
             let e_scaler = r_j.div(divisor);
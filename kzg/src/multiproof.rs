@@ -77,16 +77,16 @@ where
                     continue;
                 }
                 let bytes: Vec<u8> = element.into_bigint().to_bytes_be();
-                hasher.update(&bytes.as_slice());
+                hasher.update(bytes);
             }
 
             // Append point
             let bytes = point.into_bigint().to_bytes_be();
-            hasher.update(&bytes.as_slice());
+            hasher.update(bytes);
 
             // Append value
             let bytes = value.into_bigint().to_bytes_be();
-            hasher.update(&bytes.as_slice());
+            hasher.update(bytes);
         }
 
         // Calculate hash
@@ -102,12 +102,12 @@ where
                 continue;
             }
             let bytes: Vec<u8> = element.into_bigint().to_bytes_be();
-            hasher.update(&bytes.as_slice());
+            hasher.update(bytes);
         }
 
         // Append point
         let bytes = r.into_bigint().to_bytes_be();
-        hasher.update(&bytes.as_slice());
+        hasher.update(bytes);
 
         // Calculate hash
         let result = hasher.finalize();
@@ -122,7 +122,7 @@ where
     ) -> P {
         let mut g_poly = P::zero();
         for (j, (poly, point, value)) in izip!(polys, points, values).enumerate() {
-            let r_j = P::from_coefficients_slice(&[r.pow(&[j as u64])]);
+            let r_j = P::from_coefficients_slice(&[r.pow([j as u64])]);
             let value = P::from_coefficients_slice(&[*value]);
             let divisor = P::from_coefficients_slice(&[-*point, E::ScalarField::one()]);
             let dividend = poly.sub(&value).mul(&r_j);
@@ -145,7 +145,7 @@ where
         let mut h_poly = P::zero();
 
         for (j, (poly, point, value)) in izip!(polys, points, values).enumerate() {
-            let r_j = r.pow(&[j as u64]);
+            let r_j = r.pow([j as u64]);
             let divisor: E::ScalarField = t.sub(point);
             let y_frac = r_j.mul(value).div(&divisor);
             y = y.add(y_frac);
@@ -169,7 +169,7 @@ where
         let mut e_scalars = Vec::new();
         let mut y = E::ScalarField::zero();
         for (j, (point, value)) in izip!(points, values).enumerate() {
-            let r_j = r.pow(&[j as u64]);
+            let r_j = r.pow([j as u64]);
             let divisor: E::ScalarField = t.sub(point);
             let y_frac = r_j.mul(value).div(divisor);
             y = y.add(y_frac);
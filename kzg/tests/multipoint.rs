@@ -1,9 +1,9 @@
 use ark_bn254::{Bn254, Fr};
-use ark_ff::UniformRand;
+use ark_ff::{BigInteger, PrimeField, ToConstraintField, UniformRand, Zero};
 use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
 use ark_poly_commit::kzg10::Commitment;
 use ark_std::rand::{thread_rng, Rng};
-use keccak_asm::Keccak256;
+use keccak_asm::{Digest, Keccak256};
 use kzg_commitment::{kzg::KZG, multipoint::KZGMultipoint};
 
 type UniPoly = DensePolynomial<Fr>;
@@ -67,3 +67,50 @@ fn it_tests_multiple_points() {
 
     assert!(result);
 }
+
+#[test]
+fn it_rejects_values_traded_between_polys() {
+    let mut rng = thread_rng();
+    let degree: usize = 10;
+
+    let pp = PCS::setup(degree, false, &mut rng).unwrap();
+    let (ck, vk) = PCS::trim(pp, degree).unwrap();
+
+    // Two polys opened at the same points
+    let polys: Vec<UniPoly> = (0..2).map(|_| UniPoly::rand(degree, &mut rng)).collect();
+    let coms: Vec<Commitment<Bn254>> = polys
+        .iter()
+        .map(|poly| PCS::commit(&ck, &poly, None, None).unwrap().0)
+        .collect();
+    let points: Vec<Fr> = (0..2).map(|_| Fr::rand(&mut rng)).collect();
+
+    // If the challenge only depended on the commitments, shifting the values of the first poly
+    // by `r * delta` and those of the second by `-delta` would leave the witness unchanged
+    let mut hasher = Keccak256::new();
+    for com in coms.iter() {
+        for element in com.to_field_elements().unwrap() {
+            if !element.is_zero() {
+                hasher.update(element.into_bigint().to_bytes_be());
+            }
+        }
+    }
+    let r = Fr::from_be_bytes_mod_order(&hasher.finalize());
+    let delta = Fr::rand(&mut rng);
+    let values: Vec<Vec<Fr>> = vec![
+        points
+            .iter()
+            .map(|point| polys[0].evaluate(point) + r * delta)
+            .collect(),
+        points
+            .iter()
+            .map(|point| polys[1].evaluate(point) - delta)
+            .collect(),
+    ];
+
+    let points_vec: Vec<&[Fr]> = vec![points.as_slice(), points.as_slice()];
+    let values_vec: Vec<&[Fr]> = values.iter().map(|values| values.as_slice()).collect();
+    let proof = PCSMultiproof::prove(&ck, &polys, &coms, &points_vec, &values_vec).unwrap();
+    let result = PCSMultiproof::verify(&vk, &coms, &points_vec, &values_vec, &proof).unwrap();
+
+    assert!(!result);
+}
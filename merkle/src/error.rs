@@ -0,0 +1,46 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MerkleError {
+    /// The tree has not been committed to yet.
+    NotCommitted,
+    /// `commit` was called with an empty vector.
+    EmptyVector,
+    /// The index is not a leaf of the committed vector.
+    IndexOutOfRange(usize),
+    /// The indices of a multiproof are empty or not strictly increasing.
+    InvalidIndices,
+    /// The key is not in the sparse tree.
+    KeyNotFound,
+    /// The size is larger than the number of appended leaves.
+    SizeOutOfRange(usize),
+    /// The hasher cannot hash nodes of this width.
+    InvalidWidth(usize),
+    /// The value at this index cannot be hashed as a leaf.
+    InvalidLeaf(usize),
+}
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MerkleError::NotCommitted => write!(f, "Tree is not committed"),
+            MerkleError::EmptyVector => write!(f, "Cannot commit to an empty vector"),
+            MerkleError::IndexOutOfRange(index) => write!(f, "Index {} is out of range", index),
+            MerkleError::InvalidIndices => {
+                write!(f, "Indices must be non-empty and strictly increasing")
+            }
+            MerkleError::KeyNotFound => write!(f, "Key is not in the tree"),
+            MerkleError::SizeOutOfRange(size) => {
+                write!(f, "Size {} is larger than the tree", size)
+            }
+            MerkleError::InvalidWidth(width) => {
+                write!(f, "Width {} is not supported by the hasher", width)
+            }
+            MerkleError::InvalidLeaf(index) => {
+                write!(f, "Value at index {} is not a valid leaf", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MerkleError {}
@@ -0,0 +1,101 @@
+pub use blake3::Hasher as Blake3;
+pub use keccak_asm::Keccak256;
+pub use sha2::Sha256;
+
+pub use crate::poseidon::Poseidon;
+
+use crate::tree::{Hash, HashDomain};
+
+/// Hash function a `MerkleTree` is built with. `Keccak256` is what the `MerkleVerifier`
+/// contracts use, `Sha256` can be verified on-chain with the precompile at `0x02`, and `Poseidon`
+/// is cheap to verify in a circuit.
+pub trait MerkleHasher {
+    fn new() -> Self;
+    fn update(&mut self, data: impl AsRef<[u8]>);
+    fn finalize(self) -> Hash;
+
+    /// Whether `data` can be hashed as a leaf value or as a hash in a proof. Any bytes can by
+    /// default.
+    fn is_valid_input(_data: &[u8]) -> bool {
+        true
+    }
+
+    /// Largest number of children a node can be hashed from with `domain`, if there is one.
+    fn max_width(_domain: HashDomain) -> Option<usize> {
+        None
+    }
+}
+
+impl MerkleHasher for Keccak256 {
+    fn new() -> Self {
+        keccak_asm::Digest::new()
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        keccak_asm::Digest::update(self, data);
+    }
+
+    fn finalize(self) -> Hash {
+        keccak_asm::Digest::finalize(self).into()
+    }
+}
+
+impl MerkleHasher for Sha256 {
+    fn new() -> Self {
+        sha2::Digest::new()
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        sha2::Digest::update(self, data);
+    }
+
+    fn finalize(self) -> Hash {
+        sha2::Digest::finalize(self).into()
+    }
+}
+
+impl MerkleHasher for Blake3 {
+    fn new() -> Self {
+        Blake3::new()
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        Blake3::update(self, data.as_ref());
+    }
+
+    fn finalize(self) -> Hash {
+        Blake3::finalize(&self).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash<H: MerkleHasher>(data: &[u8]) -> Hash {
+        let mut hasher = H::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    #[test]
+    fn it_hashes_test_vectors() {
+        let to_hex = |hash: Hash| {
+            hash.iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        assert_eq!(
+            to_hex(hash::<Keccak256>(b"abc")),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+        assert_eq!(
+            to_hex(hash::<Sha256>(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            to_hex(hash::<Blake3>(b"abc")),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+}
@@ -1 +1,8 @@
 pub mod tree;
+pub mod error;
+pub mod verifier;
+pub mod hasher;
+pub mod poseidon;
+pub mod sparse;
+pub mod mmr;
+pub mod rfc6962;
@@ -0,0 +1,293 @@
+use std::marker::PhantomData;
+
+use crate::{
+    error::MerkleError,
+    hasher::{Keccak256, MerkleHasher},
+    tree::{Hash, HashDomain, Value},
+};
+
+/// Append-only Merkle accumulator (Merkle Mountain Range). The leaves form perfect binary trees
+/// of decreasing height, one per set bit of the number of leaves, whose roots (the peaks) are
+/// bagged from the right into a single root. Nodes never change once appended, so the tree of any
+/// earlier size is a prefix of the current one and can still be opened.
+#[derive(Debug)]
+pub struct MerkleMountainRange<H = Keccak256> {
+    pub leaves: Vec<Value>,
+    pub domain: HashDomain,
+    subtrees: Subtrees,
+    hasher: PhantomData<H>,
+}
+
+/// Roots of the perfect binary subtrees of an append-only list of leaves, which both the peaks
+/// of a `MerkleMountainRange` and the left side of every split of an `Rfc6962Tree` are.
+#[derive(Debug)]
+pub(crate) struct Subtrees {
+    /// `levels[h][i]` is the root of the perfect subtree over leaves `i * 2^h..(i + 1) * 2^h`.
+    levels: Vec<Vec<Hash>>,
+}
+
+impl Subtrees {
+    pub(crate) fn new() -> Self {
+        Self {
+            levels: vec![Vec::new()],
+        }
+    }
+
+    /// Appends the hash of a leaf and hashes the subtrees it completes.
+    pub(crate) fn push<H: MerkleHasher>(&mut self, domain: HashDomain, leaf_hash: Hash) {
+        self.levels[0].push(leaf_hash);
+        let mut height = 0;
+        while self.levels[height].len().is_multiple_of(2) {
+            let level = &self.levels[height];
+            let hash = domain.hash_pair::<H>(&level[level.len() - 2], &level[level.len() - 1]);
+            if self.levels.len() == height + 1 {
+                self.levels.push(Vec::new());
+            }
+            self.levels[height + 1].push(hash);
+            height += 1;
+        }
+    }
+
+    /// Root of the subtree of `2^height` leaves that contains leaf `index`.
+    pub(crate) fn root(&self, height: usize, index: usize) -> Hash {
+        self.levels[height][index >> height]
+    }
+}
+
+/// Siblings from the leaf up to its peak, then the other peaks from left to right.
+#[derive(Debug, Clone)]
+pub struct MmrProof {
+    pub siblings: Vec<Hash>,
+    pub peaks: Vec<Hash>,
+}
+
+impl MerkleMountainRange {
+    pub fn setup() -> Self {
+        Self::setup_with_hasher(HashDomain::Plain)
+    }
+}
+
+impl<H: MerkleHasher> MerkleMountainRange<H> {
+    pub fn setup_with_hasher(domain: HashDomain) -> Self {
+        Self {
+            leaves: Vec::new(),
+            domain,
+            subtrees: Subtrees::new(),
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Appends a leaf, merging the peaks of equal height it completes, and returns its index.
+    pub fn append(&mut self, value: Value) -> usize {
+        let index = self.leaves.len();
+        let leaf_hash = self.domain.hash_leaf::<H>(&value);
+        self.subtrees.push::<H>(self.domain, leaf_hash);
+        self.leaves.push(value);
+        index
+    }
+
+    /// Bagged root of the current leaves. For a power of two number of leaves above one, this is
+    /// the `root_hash()` of a binary `MerkleTree` over the same values and domain.
+    pub fn root_hash(&self) -> Option<Hash> {
+        self.root_hash_at(self.leaf_count())
+    }
+
+    /// Bagged root of the first `size` leaves.
+    pub fn root_hash_at(&self, size: usize) -> Option<Hash> {
+        if size == 0 || size > self.leaf_count() {
+            return None;
+        }
+        let peaks = peaks(size)
+            .into_iter()
+            .map(|(height, start)| self.subtrees.root(height, start))
+            .collect::<Vec<_>>();
+        Some(bag_peaks::<H>(self.domain, &peaks))
+    }
+
+    pub fn open(&self, index: usize) -> Result<(Value, MmrProof), MerkleError> {
+        self.open_at(index, self.leaf_count())
+    }
+
+    /// Opens `index` against the root of the first `size` leaves.
+    pub fn open_at(&self, index: usize, size: usize) -> Result<(Value, MmrProof), MerkleError> {
+        if size > self.leaf_count() {
+            return Err(MerkleError::SizeOutOfRange(size));
+        }
+        if index >= size {
+            return Err(MerkleError::IndexOutOfRange(index));
+        }
+
+        let mut siblings = Vec::new();
+        let mut peak_hashes = Vec::new();
+        for (height, start) in peaks(size) {
+            if (start..start + (1 << height)).contains(&index) {
+                for level in 0..height {
+                    siblings.push(self.subtrees.root(level, index ^ (1 << level)));
+                }
+            } else {
+                peak_hashes.push(self.subtrees.root(height, start));
+            }
+        }
+
+        Ok((
+            self.leaves[index].clone(),
+            MmrProof {
+                siblings,
+                peaks: peak_hashes,
+            },
+        ))
+    }
+
+    pub fn verify(&self, index: usize, value: &Value, proof: &MmrProof) -> bool {
+        match self.root_hash() {
+            Some(root) => {
+                Self::verify_with_root(&root, self.domain, self.leaf_count(), index, value, proof)
+            }
+            None => false,
+        }
+    }
+
+    /// Verifies a proof against the root of a range of `size` leaves without the range.
+    pub fn verify_with_root(
+        root: &Hash,
+        domain: HashDomain,
+        size: usize,
+        index: usize,
+        value: &Value,
+        proof: &MmrProof,
+    ) -> bool {
+        if index >= size {
+            return false;
+        }
+        let peaks = peaks(size);
+        let Some(position) = peaks
+            .iter()
+            .position(|&(height, start)| (start..start + (1 << height)).contains(&index))
+        else {
+            return false;
+        };
+        let (height, _) = peaks[position];
+        if proof.siblings.len() != height || proof.peaks.len() != peaks.len() - 1 {
+            return false;
+        }
+
+        let mut calculated_hash = domain.hash_leaf::<H>(value);
+        for (level, sibling) in proof.siblings.iter().enumerate() {
+            calculated_hash = if (index >> level) & 1 == 0 {
+                domain.hash_pair::<H>(&calculated_hash, sibling)
+            } else {
+                domain.hash_pair::<H>(sibling, &calculated_hash)
+            };
+        }
+        let mut peak_hashes = proof.peaks.clone();
+        peak_hashes.insert(position, calculated_hash);
+
+        bag_peaks::<H>(domain, &peak_hashes) == *root
+    }
+}
+
+/// Peaks of a range of `size` leaves from left to right, as `(height, first leaf)`.
+fn peaks(size: usize) -> Vec<(usize, usize)> {
+    let mut peaks = Vec::new();
+    let mut start = 0;
+    for height in (0..usize::BITS as usize).rev() {
+        if (size >> height) & 1 == 1 {
+            peaks.push((height, start));
+            start += 1 << height;
+        }
+    }
+    peaks
+}
+
+/// Folds the peaks from the right, `H(p_0, H(p_1, .. H(p_n-1, p_n)))`.
+fn bag_peaks<H: MerkleHasher>(domain: HashDomain, peaks: &[Hash]) -> Hash {
+    let (last, rest) = peaks.split_last().unwrap();
+    rest.iter()
+        .rev()
+        .fold(*last, |bag, peak| domain.hash_pair::<H>(peak, &bag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::MerkleTree;
+    use rand::Rng;
+
+    #[test]
+    fn it_verifies_every_leaf() {
+        let mut mmr = MerkleMountainRange::setup();
+        for i in 0..37 {
+            assert_eq!(mmr.append(random_bytes()), i);
+        }
+        assert_eq!(peaks(37).len(), 3);
+
+        for index in 0..37 {
+            let (value, proof) = mmr.open(index).unwrap();
+            assert!(mmr.verify(index, &value, &proof));
+            assert!(!mmr.verify(index, &random_bytes(), &proof));
+            assert!(!mmr.verify((index + 1) % 37, &value, &proof));
+        }
+    }
+
+    #[test]
+    fn it_verifies_against_historical_roots() {
+        let mut mmr = MerkleMountainRange::setup();
+        let mut roots = Vec::new();
+        for _ in 0..20 {
+            mmr.append(random_bytes());
+            roots.push(mmr.root_hash().unwrap());
+        }
+        for _ in 0..20 {
+            mmr.append(random_bytes());
+        }
+
+        for size in 1..=20 {
+            assert_eq!(mmr.root_hash_at(size), Some(roots[size - 1]));
+            let index = rand::thread_rng().gen_range(0..size);
+            let (value, proof) = mmr.open_at(index, size).unwrap();
+            let verify = |root: &Hash| {
+                MerkleMountainRange::<Keccak256>::verify_with_root(
+                    root,
+                    HashDomain::Plain,
+                    size,
+                    index,
+                    &value,
+                    &proof,
+                )
+            };
+            assert!(verify(&roots[size - 1]));
+            assert!(!verify(&mmr.root_hash().unwrap()));
+        }
+        assert_eq!(
+            mmr.open_at(5, 41).unwrap_err(),
+            MerkleError::SizeOutOfRange(41)
+        );
+        assert_eq!(
+            mmr.open_at(5, 5).unwrap_err(),
+            MerkleError::IndexOutOfRange(5)
+        );
+    }
+
+    #[test]
+    fn it_matches_binary_tree_root() {
+        let vec = (0..16).map(|_| random_bytes()).collect::<Vec<_>>();
+        let mut mmr = MerkleMountainRange::<Keccak256>::setup_with_hasher(HashDomain::SEPARATED);
+        for value in vec.iter() {
+            mmr.append(value.clone());
+        }
+        let vec = vec.iter().collect::<Vec<_>>();
+        let mut tree = MerkleTree::setup_with_domain(2, HashDomain::SEPARATED);
+        tree.commit(vec.as_slice()).unwrap();
+
+        assert_eq!(mmr.root_hash(), tree.root_hash());
+    }
+
+    fn random_bytes() -> Value {
+        let mut rng = rand::thread_rng();
+        (0..32).map(|_| rng.gen()).collect()
+    }
+}
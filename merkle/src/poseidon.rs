@@ -0,0 +1,339 @@
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, BigInteger256, Field, PrimeField, Zero};
+use std::{collections::VecDeque, sync::OnceLock};
+
+use crate::{
+    hasher::MerkleHasher,
+    tree::{Hash, HashDomain},
+};
+
+/// Largest number of inputs with circomlib parameters that are checked against the reference.
+pub const MAX_INPUTS: usize = 12;
+
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: [usize; MAX_INPUTS] = [56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65];
+
+static PARAMETERS: [OnceLock<PoseidonParameters>; MAX_INPUTS] =
+    [const { OnceLock::new() }; MAX_INPUTS];
+
+/// Poseidon over the BN254 scalar field with the parameters of circomlib's `Poseidon(n)`, as a
+/// `MerkleHasher`. Every `update` absorbs its data as field elements of 32 big-endian bytes, a
+/// shorter last chunk being an element of its own, so with `HashDomain::Plain` a leaf `v` is
+/// hashed as `Poseidon(v)` and a node as `Poseidon(children)`. Leaves are field elements encoded
+/// with `fr_to_hash`, and at most `MAX_INPUTS` elements can be absorbed, which bounds the width of
+/// the tree. `MerkleTree` and `MerkleVerifier` check both, other uses panic on a 32-byte chunk
+/// that is not a canonical encoding, which would alias the element it reduces to.
+#[derive(Debug, Clone, Default)]
+pub struct Poseidon {
+    inputs: Vec<Fr>,
+}
+
+impl MerkleHasher for Poseidon {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        for chunk in data.as_ref().chunks(32) {
+            let element = Fr::from_be_bytes_mod_order(chunk);
+            assert!(
+                chunk.len() < 32 || fr_to_hash(&element) == chunk,
+                "Poseidon input is not a canonical field element"
+            );
+            self.inputs.push(element);
+        }
+    }
+
+    fn finalize(self) -> Hash {
+        fr_to_hash(&poseidon_hash(&self.inputs))
+    }
+
+    fn is_valid_input(data: &[u8]) -> bool {
+        data.len() == 32 && fr_to_hash(&Fr::from_be_bytes_mod_order(data)) == data
+    }
+
+    /// The domain prefix takes one of the `MAX_INPUTS` elements.
+    fn max_width(domain: HashDomain) -> Option<usize> {
+        match domain {
+            HashDomain::Plain => Some(MAX_INPUTS),
+            HashDomain::Prefixed { .. } => Some(MAX_INPUTS - 1),
+        }
+    }
+}
+
+/// Hashes 1 to `MAX_INPUTS` field elements like circomlib's `Poseidon(inputs.len())`. Panics for
+/// any other number of inputs.
+pub fn poseidon_hash(inputs: &[Fr]) -> Fr {
+    if inputs.is_empty() || inputs.len() > MAX_INPUTS {
+        panic!("Poseidon takes 1 to {} inputs", MAX_INPUTS)
+    }
+    let parameters =
+        PARAMETERS[inputs.len() - 1].get_or_init(|| PoseidonParameters::circom(inputs.len() + 1));
+    parameters.permute(inputs)
+}
+
+/// Encodes a leaf as the 32 big-endian bytes a `MerkleTree<Poseidon>` commits to.
+pub fn fr_to_hash(value: &Fr) -> Hash {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&value.into_bigint().to_bytes_be());
+    hash
+}
+
+pub fn hash_to_fr(hash: &Hash) -> Fr {
+    Fr::from_be_bytes_mod_order(hash)
+}
+
+#[derive(Debug)]
+struct PoseidonParameters {
+    width: usize,
+    partial_rounds: usize,
+    round_constants: Vec<Fr>,
+    mds: Vec<Vec<Fr>>,
+}
+
+impl PoseidonParameters {
+    /// Generates the round constants and MDS matrix for a state of `width` elements with the
+    /// Grain LFSR of the reference `generate_parameters_grain.sage`, which circomlib uses.
+    fn circom(width: usize) -> Self {
+        let partial_rounds = PARTIAL_ROUNDS[width - 2];
+        let mut grain = Grain::new(width, FULL_ROUNDS, partial_rounds);
+
+        let round_constants = (0..(FULL_ROUNDS + partial_rounds) * width)
+            .map(|_| loop {
+                if let Some(constant) = Fr::from_bigint(grain.next_bigint()) {
+                    break constant;
+                }
+            })
+            .collect();
+
+        // Cauchy matrix 1 / (x_i + y_j) from 2 * width distinct elements
+        let mds = loop {
+            let elements: Vec<Fr> = (0..2 * width)
+                .map(|_| Fr::from_be_bytes_mod_order(&grain.next_bigint().to_bytes_be()))
+                .collect();
+            let (xs, ys) = elements.split_at(width);
+            let distinct = elements
+                .iter()
+                .enumerate()
+                .all(|(i, x)| !elements[..i].contains(x));
+            if !distinct || xs.iter().any(|x| ys.iter().any(|y| (*x + y).is_zero())) {
+                continue;
+            }
+            break xs
+                .iter()
+                .map(|x| ys.iter().map(|y| (*x + y).inverse().unwrap()).collect())
+                .collect();
+        };
+
+        Self {
+            width,
+            partial_rounds,
+            round_constants,
+            mds,
+        }
+    }
+
+    fn permute(&self, inputs: &[Fr]) -> Fr {
+        let mut state = vec![Fr::zero()];
+        state.extend_from_slice(inputs);
+
+        let half_full_rounds = FULL_ROUNDS / 2;
+        for round in 0..FULL_ROUNDS + self.partial_rounds {
+            for (i, element) in state.iter_mut().enumerate() {
+                *element += self.round_constants[round * self.width + i];
+            }
+            if round < half_full_rounds || round >= half_full_rounds + self.partial_rounds {
+                state
+                    .iter_mut()
+                    .for_each(|element| *element = element.pow([5]));
+            } else {
+                state[0] = state[0].pow([5]);
+            }
+            state = self
+                .mds
+                .iter()
+                .map(|row| row.iter().zip(state.iter()).map(|(m, s)| *m * s).sum())
+                .collect();
+        }
+        state[0]
+    }
+}
+
+struct Grain {
+    bits: VecDeque<bool>,
+}
+
+impl Grain {
+    fn new(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        // Prime field, x^5 S-box, 254-bit field, then the instance, padded with ones
+        let mut bits = VecDeque::new();
+        for (value, length) in [
+            (1, 2),
+            (0, 4),
+            (Fr::MODULUS_BIT_SIZE as usize, 12),
+            (width, 12),
+            (full_rounds, 10),
+            (partial_rounds, 10),
+        ] {
+            bits.extend((0..length).rev().map(|i| (value >> i) & 1 == 1));
+        }
+        bits.extend([true; 30]);
+
+        let mut grain = Self { bits };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let bit = [62, 51, 38, 23, 13, 0]
+            .iter()
+            .fold(false, |bit, &i| bit ^ self.bits[i]);
+        self.bits.pop_front();
+        self.bits.push_back(bit);
+        bit
+    }
+
+    /// Outputs the bit after a set bit and discards the bit after an unset one.
+    fn next_bit(&mut self) -> bool {
+        loop {
+            if self.step() {
+                return self.step();
+            }
+            self.step();
+        }
+    }
+
+    fn next_bigint(&mut self) -> BigInteger256 {
+        let bits: Vec<bool> = (0..Fr::MODULUS_BIT_SIZE).map(|_| self.next_bit()).collect();
+        BigInteger256::from_bits_be(&bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::MerkleError,
+        tree::{MerkleTree, Padding},
+    };
+
+    fn from_hex(hex: &str) -> Fr {
+        let bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        Fr::from_be_bytes_mod_order(&bytes)
+    }
+
+    #[test]
+    fn it_hashes_circomlib_test_vectors() {
+        let inputs = |n: u64| (1..=n).map(Fr::from).collect::<Vec<_>>();
+        assert_eq!(
+            poseidon_hash(&inputs(1)),
+            from_hex("29176100eaa962bdc1fe6c654d6a3c130e96a4d1168b33848b897dc502820133")
+        );
+        assert_eq!(
+            poseidon_hash(&inputs(2)),
+            from_hex("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a")
+        );
+        assert_eq!(
+            poseidon_hash(&inputs(4)),
+            from_hex("299c867db6c1fdd79dcefa40e4510b9837e60ebb1ce0663dbaa525df65250465")
+        );
+        assert_eq!(
+            poseidon_hash(&[Fr::from(1); MAX_INPUTS]),
+            from_hex("14390be0baef249bd47c65ddac65c2e52e8513c081c1cd72c98006098e9a8fbe")
+        );
+    }
+
+    #[test]
+    fn it_generates_circomlib_parameters() {
+        // First round constant and MDS entry of circomlib's `poseidon_constants.json` for t = 3
+        let parameters = PoseidonParameters::circom(3);
+        assert_eq!(
+            parameters.round_constants[0],
+            from_hex("0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e")
+        );
+        assert_eq!(
+            parameters.mds[0][0],
+            from_hex("109b7f411ba0e4c9b2b70caf5c36a7b194be7c11ad24378bfedb68592ba8118b")
+        );
+    }
+
+    #[test]
+    fn it_commits_field_elements() {
+        let leaves = (1..=4).map(Fr::from).collect::<Vec<_>>();
+        let vec = leaves
+            .iter()
+            .map(|leaf| fr_to_hash(leaf).to_vec())
+            .collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        let mut tree: MerkleTree<Poseidon> =
+            MerkleTree::setup_with_hasher(2, HashDomain::Plain, Padding::RepeatLast).unwrap();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let leaf_hash = |leaf: &Fr| poseidon_hash(&[*leaf]);
+        let left = poseidon_hash(&[leaf_hash(&leaves[0]), leaf_hash(&leaves[1])]);
+        let right = poseidon_hash(&[leaf_hash(&leaves[2]), leaf_hash(&leaves[3])]);
+        let root = poseidon_hash(&[left, right]);
+        assert_eq!(hash_to_fr(&tree.root_hash().unwrap()), root);
+
+        let (value, proof) = tree.open(2).unwrap();
+        assert_eq!(hash_to_fr(&value.try_into().unwrap()), leaves[2]);
+        assert!(tree.verify(2, &fr_to_hash(&leaves[2]).to_vec(), &proof));
+    }
+
+    #[test]
+    fn it_rejects_non_canonical_leaves() {
+        let mut tree: MerkleTree<Poseidon> =
+            MerkleTree::setup_with_hasher(2, HashDomain::Plain, Padding::RepeatLast).unwrap();
+        let value = fr_to_hash(&Fr::from(1)).to_vec();
+
+        // 1 + p reduces to the same element as 1
+        let mut aliased_value = Fr::MODULUS.to_bytes_be();
+        aliased_value[31] += 1;
+        for (index, invalid_value) in [(1, aliased_value.clone()), (0, vec![]), (1, vec![1])] {
+            let mut vec = [value.clone(), value.clone()];
+            vec[index] = invalid_value;
+            let vec = vec.iter().collect::<Vec<_>>();
+            assert_eq!(
+                tree.commit(vec.as_slice()).unwrap_err(),
+                MerkleError::InvalidLeaf(index)
+            );
+        }
+
+        tree.commit(&[&value, &value]).unwrap();
+        assert_eq!(
+            tree.update(1, aliased_value.clone()).unwrap_err(),
+            MerkleError::InvalidLeaf(1)
+        );
+        let (_, proof) = tree.open(0).unwrap();
+        assert!(!tree.verify(0, &aliased_value, &proof));
+        let mut aliased_proof = proof.clone();
+        aliased_proof.hashes[0] = aliased_value.try_into().unwrap();
+        assert!(!tree.verify(0, &value, &aliased_proof));
+    }
+
+    #[test]
+    fn it_rejects_unsupported_widths() {
+        let setup = |width, domain| {
+            MerkleTree::<Poseidon>::setup_with_hasher(width, domain, Padding::RepeatLast)
+                .map(|tree| tree.k)
+        };
+        assert_eq!(setup(MAX_INPUTS, HashDomain::Plain), Ok(MAX_INPUTS));
+        assert_eq!(
+            setup(MAX_INPUTS + 1, HashDomain::Plain),
+            Err(MerkleError::InvalidWidth(MAX_INPUTS + 1))
+        );
+        assert_eq!(
+            setup(MAX_INPUTS, HashDomain::SEPARATED),
+            Err(MerkleError::InvalidWidth(MAX_INPUTS))
+        );
+        assert_eq!(
+            setup(1, HashDomain::Plain),
+            Err(MerkleError::InvalidWidth(1))
+        );
+    }
+}
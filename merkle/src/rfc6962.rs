@@ -0,0 +1,501 @@
+use std::marker::PhantomData;
+
+use crate::{
+    error::MerkleError,
+    hasher::{MerkleHasher, Sha256},
+    mmr::Subtrees,
+    tree::{Hash, HashDomain, MerkleProof, Value},
+};
+
+/// Leaves are hashed as `H(0x00 || value)` and internal nodes as `H(0x01 || left || right)`.
+const DOMAIN: HashDomain = HashDomain::SEPARATED;
+
+/// Append-only binary Merkle tree of RFC 6962 (Certificate Transparency). A tree of `n` leaves
+/// splits at the largest power of two below `n`, so the right edge is unbalanced instead of
+/// padded, and the tree of any earlier size is a prefix of the current one.
+#[derive(Debug)]
+pub struct Rfc6962Tree<H = Sha256> {
+    pub leaves: Vec<Value>,
+    /// Every subtree on the left of a split, so a root or a proof costs `O(log n)` hashes.
+    subtrees: Subtrees,
+    hasher: PhantomData<H>,
+}
+
+/// Hashes proving that the tree of one size is a prefix of the tree of a larger size.
+#[derive(Debug, Clone)]
+pub struct ConsistencyProof {
+    pub hashes: Vec<Hash>,
+}
+
+impl Rfc6962Tree {
+    pub fn setup() -> Self {
+        Self::setup_with_hasher()
+    }
+}
+
+impl<H: MerkleHasher> Rfc6962Tree<H> {
+    pub fn setup_with_hasher() -> Self {
+        Self {
+            leaves: Vec::new(),
+            subtrees: Subtrees::new(),
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Appends a leaf, hashing the perfect subtrees it completes, and returns its index.
+    pub fn append(&mut self, value: Value) -> usize {
+        let index = self.leaves.len();
+        let leaf_hash = DOMAIN.hash_leaf::<H>(&value);
+        self.subtrees.push::<H>(DOMAIN, leaf_hash);
+        self.leaves.push(value);
+        index
+    }
+
+    pub fn root_hash(&self) -> Hash {
+        self.subtree_hash(0, self.leaf_count())
+    }
+
+    /// Root of the tree of the first `size` leaves.
+    pub fn root_hash_at(&self, size: usize) -> Option<Hash> {
+        if size > self.leaf_count() {
+            return None;
+        }
+        Some(self.subtree_hash(0, size))
+    }
+
+    pub fn open(&self, index: usize) -> Result<(Value, MerkleProof), MerkleError> {
+        self.open_at(index, self.leaf_count())
+    }
+
+    /// Opens `index` against the tree of the first `size` leaves, `PATH(index, D[size])` of the
+    /// RFC, with the siblings from the leaf up.
+    pub fn open_at(&self, index: usize, size: usize) -> Result<(Value, MerkleProof), MerkleError> {
+        if size > self.leaf_count() {
+            return Err(MerkleError::SizeOutOfRange(size));
+        }
+        if index >= size {
+            return Err(MerkleError::IndexOutOfRange(index));
+        }
+
+        let mut hashes = Vec::new();
+        self.path(index, 0, size, &mut hashes);
+        Ok((self.leaves[index].clone(), MerkleProof { hashes }))
+    }
+
+    /// Proves that the tree of the first `first` leaves is a prefix of the tree of the first
+    /// `second` leaves, `PROOF(first, D[second])` of the RFC.
+    pub fn prove_consistency(
+        &self,
+        first: usize,
+        second: usize,
+    ) -> Result<ConsistencyProof, MerkleError> {
+        if second > self.leaf_count() {
+            return Err(MerkleError::SizeOutOfRange(second));
+        }
+        if first == 0 || first > second {
+            return Err(MerkleError::SizeOutOfRange(first));
+        }
+
+        let mut hashes = Vec::new();
+        self.subproof(first, 0, second, true, &mut hashes);
+        Ok(ConsistencyProof { hashes })
+    }
+
+    pub fn verify(&self, index: usize, value: &Value, proof: &MerkleProof) -> bool {
+        Self::verify_inclusion(&self.root_hash(), self.leaf_count(), index, value, proof)
+    }
+
+    /// Verifies an inclusion proof against the root of a tree of `size` leaves, as in section
+    /// 2.1.3.2 of RFC 9162.
+    pub fn verify_inclusion(
+        root: &Hash,
+        size: usize,
+        index: usize,
+        value: &Value,
+        proof: &MerkleProof,
+    ) -> bool {
+        if index >= size {
+            return false;
+        }
+        // Index of the current node and of the last node of its level
+        let (mut node, mut last) = (index, size - 1);
+        let mut calculated_hash = DOMAIN.hash_leaf::<H>(value);
+        for hash in proof.hashes.iter() {
+            if last == 0 {
+                return false;
+            }
+            if node & 1 == 1 || node == last {
+                calculated_hash = DOMAIN.hash_pair::<H>(hash, &calculated_hash);
+                while node & 1 == 0 && node != 0 {
+                    node >>= 1;
+                    last >>= 1;
+                }
+            } else {
+                calculated_hash = DOMAIN.hash_pair::<H>(&calculated_hash, hash);
+            }
+            node >>= 1;
+            last >>= 1;
+        }
+
+        last == 0 && calculated_hash == *root
+    }
+
+    /// Verifies that `first_root` is the root of a prefix of `first` leaves of the tree of
+    /// `second` leaves with `second_root`, as in section 2.1.4.2 of RFC 9162.
+    pub fn verify_consistency(
+        first: usize,
+        second: usize,
+        first_root: &Hash,
+        second_root: &Hash,
+        proof: &ConsistencyProof,
+    ) -> bool {
+        if first == 0 || first > second {
+            return false;
+        }
+        if first == second {
+            return proof.hashes.is_empty() && first_root == second_root;
+        }
+
+        // The proof leaves out the root of the first tree when it is a complete subtree
+        let mut hashes = proof.hashes.iter();
+        let seed = if first.is_power_of_two() {
+            first_root
+        } else {
+            match hashes.next() {
+                Some(hash) => hash,
+                None => return false,
+            }
+        };
+        let (mut node, mut last) = (first - 1, second - 1);
+        while node & 1 == 1 {
+            node >>= 1;
+            last >>= 1;
+        }
+
+        let (mut first_hash, mut second_hash) = (*seed, *seed);
+        for hash in hashes {
+            if last == 0 {
+                return false;
+            }
+            if node & 1 == 1 || node == last {
+                first_hash = DOMAIN.hash_pair::<H>(hash, &first_hash);
+                second_hash = DOMAIN.hash_pair::<H>(hash, &second_hash);
+                while node & 1 == 0 && node != 0 {
+                    node >>= 1;
+                    last >>= 1;
+                }
+            } else {
+                second_hash = DOMAIN.hash_pair::<H>(&second_hash, hash);
+            }
+            node >>= 1;
+            last >>= 1;
+        }
+
+        last == 0 && first_hash == *first_root && second_hash == *second_root
+    }
+
+    /// `MTH(D[start:start + n])` of the RFC. The left side of every split is a perfect subtree
+    /// read from `subtrees`, so only the right edge is hashed.
+    fn subtree_hash(&self, start: usize, n: usize) -> Hash {
+        match n {
+            0 => H::new().finalize(),
+            n if n.is_power_of_two() => self.subtrees.root(n.trailing_zeros() as usize, start),
+            n => {
+                let k = split_point(n);
+                DOMAIN.hash_pair::<H>(
+                    &self.subtree_hash(start, k),
+                    &self.subtree_hash(start + k, n - k),
+                )
+            }
+        }
+    }
+
+    /// `PATH(index, D[start:start + n])` of the RFC, with `index` relative to `start`.
+    fn path(&self, index: usize, start: usize, n: usize, hashes: &mut Vec<Hash>) {
+        if n == 1 {
+            return;
+        }
+        let k = split_point(n);
+        if index < k {
+            self.path(index, start, k, hashes);
+            hashes.push(self.subtree_hash(start + k, n - k));
+        } else {
+            self.path(index - k, start + k, n - k, hashes);
+            hashes.push(self.subtree_hash(start, k));
+        }
+    }
+
+    /// `SUBPROOF(m, D[start:start + n], complete)` of the RFC.
+    fn subproof(&self, m: usize, start: usize, n: usize, complete: bool, hashes: &mut Vec<Hash>) {
+        if m == n {
+            if !complete {
+                hashes.push(self.subtree_hash(start, n));
+            }
+            return;
+        }
+        let k = split_point(n);
+        if m <= k {
+            self.subproof(m, start, k, complete, hashes);
+            hashes.push(self.subtree_hash(start + k, n - k));
+        } else {
+            self.subproof(m - k, start + k, n - k, false, hashes);
+            hashes.push(self.subtree_hash(start, k));
+        }
+    }
+}
+
+/// Largest power of two below `n`, for `n > 1`.
+fn split_point(n: usize) -> usize {
+    1 << (n - 1).ilog2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Tree = Rfc6962Tree<Sha256>;
+
+    fn from_hex(hex: &str) -> Hash {
+        let mut hash = [0; 32];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        hash
+    }
+
+    /// Leaves and roots of the reference test data of certificate-transparency and Trillian.
+    fn reference_tree() -> (Tree, Vec<Hash>) {
+        let leaves: [&[u8]; 8] = [
+            b"",
+            b"\x00",
+            b"\x10",
+            b"\x20\x21",
+            b"\x30\x31",
+            b"\x40\x41\x42\x43",
+            b"\x50\x51\x52\x53\x54\x55\x56\x57",
+            b"\x60\x61\x62\x63\x64\x65\x66\x67\x68\x69\x6a\x6b\x6c\x6d\x6e\x6f",
+        ];
+        let roots = [
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+            "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+            "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+            "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+            "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+            "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+        ];
+        let mut tree = Rfc6962Tree::setup();
+        for leaf in leaves {
+            tree.append(leaf.to_vec());
+        }
+        (tree, roots.iter().map(|root| from_hex(root)).collect())
+    }
+
+    /// `MTH(D[n])` hashed from the leaves, without the cached subtrees.
+    fn naive_root(values: &[Value]) -> Hash {
+        match values.len() {
+            0 => Sha256::new().finalize(),
+            1 => DOMAIN.hash_leaf::<Sha256>(&values[0]),
+            n => {
+                let (left, right) = values.split_at(split_point(n));
+                DOMAIN.hash_pair::<Sha256>(&naive_root(left), &naive_root(right))
+            }
+        }
+    }
+
+    #[test]
+    fn it_computes_reference_roots() {
+        let (tree, roots) = reference_tree();
+        assert_eq!(
+            tree.root_hash_at(0),
+            Some(from_hex(
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+            ))
+        );
+        for size in 1..=8 {
+            assert_eq!(tree.root_hash_at(size), Some(roots[size - 1]));
+        }
+        assert_eq!(tree.root_hash_at(9), None);
+    }
+
+    #[test]
+    fn it_verifies_reference_inclusion_proofs() {
+        let (tree, roots) = reference_tree();
+        let cases = [
+            (
+                0,
+                8,
+                vec![
+                    "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                    "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                    "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+                ],
+            ),
+            (
+                5,
+                8,
+                vec![
+                    "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                    "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                    "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+                ],
+            ),
+            (
+                2,
+                3,
+                vec!["fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125"],
+            ),
+            (0, 1, vec![]),
+        ];
+        for (index, size, expected) in cases {
+            let (value, proof) = tree.open_at(index, size).unwrap();
+            let expected = expected
+                .iter()
+                .map(|hash| from_hex(hash))
+                .collect::<Vec<_>>();
+            assert_eq!(proof.hashes, expected);
+            assert!(Tree::verify_inclusion(
+                &roots[size - 1],
+                size,
+                index,
+                &value,
+                &proof
+            ));
+            assert!(!Tree::verify_inclusion(
+                &roots[size - 1],
+                size + 1,
+                index,
+                &value,
+                &proof
+            ));
+        }
+
+        // Every leaf of every size
+        for size in 1..=8 {
+            for index in 0..size {
+                let (value, proof) = tree.open_at(index, size).unwrap();
+                assert!(Tree::verify_inclusion(
+                    &roots[size - 1],
+                    size,
+                    index,
+                    &value,
+                    &proof
+                ));
+                assert!(!Tree::verify_inclusion(
+                    &roots[size - 1],
+                    size,
+                    index,
+                    &vec![0xff],
+                    &proof
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn it_verifies_reference_consistency_proofs() {
+        let (tree, roots) = reference_tree();
+        let cases = [
+            (
+                1,
+                8,
+                vec![
+                    "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                    "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                    "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+                ],
+            ),
+            (
+                6,
+                8,
+                vec![
+                    "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                    "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                    "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+                ],
+            ),
+            (
+                2,
+                5,
+                vec![
+                    "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                    "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                ],
+            ),
+        ];
+        for (first, second, expected) in cases {
+            let proof = tree.prove_consistency(first, second).unwrap();
+            let expected = expected
+                .iter()
+                .map(|hash| from_hex(hash))
+                .collect::<Vec<_>>();
+            assert_eq!(proof.hashes, expected);
+        }
+
+        // Every pair of sizes
+        for second in 1..=8 {
+            for first in 1..=second {
+                let proof = tree.prove_consistency(first, second).unwrap();
+                let (first_root, second_root) = (&roots[first - 1], &roots[second - 1]);
+                assert!(Tree::verify_consistency(
+                    first,
+                    second,
+                    first_root,
+                    second_root,
+                    &proof
+                ));
+                if first < second {
+                    assert!(!Tree::verify_consistency(
+                        first,
+                        second,
+                        second_root,
+                        second_root,
+                        &proof
+                    ));
+                }
+            }
+        }
+        assert_eq!(
+            tree.prove_consistency(0, 8).unwrap_err(),
+            MerkleError::SizeOutOfRange(0)
+        );
+        assert_eq!(
+            tree.prove_consistency(3, 9).unwrap_err(),
+            MerkleError::SizeOutOfRange(9)
+        );
+    }
+
+    #[test]
+    fn it_proves_with_cached_subtrees() {
+        let mut tree = Tree::setup();
+        for i in 0..300u32 {
+            tree.append(i.to_be_bytes().to_vec());
+        }
+
+        for size in [1, 2, 3, 64, 100, 255, 256, 257, 300] {
+            let root = tree.root_hash_at(size).unwrap();
+            assert_eq!(root, naive_root(&tree.leaves[..size]));
+            for index in [0, size / 2, size - 1] {
+                let (value, proof) = tree.open_at(index, size).unwrap();
+                assert!(Tree::verify_inclusion(&root, size, index, &value, &proof));
+            }
+            for first in [1, size / 2 + 1, size] {
+                let proof = tree.prove_consistency(first, size).unwrap();
+                let first_root = tree.root_hash_at(first).unwrap();
+                assert!(Tree::verify_consistency(
+                    first,
+                    size,
+                    &first_root,
+                    &root,
+                    &proof
+                ));
+            }
+        }
+        assert_eq!(tree.root_hash(), naive_root(&tree.leaves));
+    }
+}
@@ -0,0 +1,287 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use crate::{
+    error::MerkleError,
+    hasher::{Keccak256, MerkleHasher},
+    tree::{Hash, HashDomain, Value},
+};
+
+pub type Key = [u8; 32];
+
+/// Number of levels below the root, one per bit of the key.
+pub const DEPTH: usize = 256;
+
+/// Binary Merkle tree over the `2^256` slots of 256-bit keys where only populated paths are
+/// stored. The bits of a key pick the path from the root, most significant first. An empty leaf
+/// hashes to `[0; 32]` and every empty subtree is represented by a precomputed default hash of its
+/// level.
+#[derive(Debug)]
+pub struct SparseMerkleTree<H = Keccak256> {
+    pub nodes: HashMap<(usize, Key), Hash>,
+    pub leaves: HashMap<Key, Value>,
+    pub domain: HashDomain,
+    default_hashes: Vec<Hash>,
+    hasher: PhantomData<H>,
+}
+
+/// Siblings of a path from the leaf up. Bit `d - 1` of `bitmap` is set if the sibling at depth
+/// `d` is not the default hash of its level, and only those siblings are in `hashes`.
+#[derive(Debug, Clone)]
+pub struct SparseMerkleProof {
+    pub bitmap: [u8; 32],
+    pub hashes: Vec<Hash>,
+}
+
+impl SparseMerkleTree {
+    pub fn setup() -> Self {
+        Self::setup_with_hasher(HashDomain::Plain)
+    }
+}
+
+impl<H: MerkleHasher> SparseMerkleTree<H> {
+    pub fn setup_with_hasher(domain: HashDomain) -> Self {
+        Self {
+            nodes: HashMap::new(),
+            leaves: HashMap::new(),
+            domain,
+            default_hashes: Self::compute_defaults(domain),
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn root_hash(&self) -> Hash {
+        self.node_hash(0, &[0; 32])
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&Value> {
+        self.leaves.get(key)
+    }
+
+    /// Sets the value at `key` and returns the previous one.
+    pub fn insert(&mut self, key: Key, value: Value) -> Option<Value> {
+        let leaf_hash = self.domain.hash_leaf::<H>(&value);
+        self.nodes.insert((DEPTH, key), leaf_hash);
+        let previous = self.leaves.insert(key, value);
+        self.rehash_path(&key);
+        previous
+    }
+
+    /// Replaces the value at a key that is already in the tree and returns the previous one.
+    pub fn update(&mut self, key: Key, value: Value) -> Result<Value, MerkleError> {
+        if !self.leaves.contains_key(&key) {
+            return Err(MerkleError::KeyNotFound);
+        }
+        Ok(self.insert(key, value).unwrap())
+    }
+
+    /// Empties the leaf at `key` and returns its value.
+    pub fn delete(&mut self, key: &Key) -> Option<Value> {
+        let previous = self.leaves.remove(key)?;
+        self.nodes.remove(&(DEPTH, *key));
+        self.rehash_path(key);
+        Some(previous)
+    }
+
+    /// Opens `key`, proving membership of its value or, if it holds none, non-membership.
+    pub fn open(&self, key: &Key) -> (Option<Value>, SparseMerkleProof) {
+        let mut bitmap = [0; 32];
+        let mut hashes = Vec::new();
+        let mut position = *key;
+        for depth in (1..=DEPTH).rev() {
+            let mut sibling = position;
+            flip_bit(&mut sibling, depth - 1);
+            if let Some(hash) = self.nodes.get(&(depth, sibling)) {
+                flip_bit(&mut bitmap, depth - 1);
+                hashes.push(*hash);
+            }
+            clear_bit(&mut position, depth - 1);
+        }
+        (self.get(key).cloned(), SparseMerkleProof { bitmap, hashes })
+    }
+
+    pub fn verify(&self, key: &Key, value: Option<&Value>, proof: &SparseMerkleProof) -> bool {
+        Self::verify_with_root(&self.root_hash(), self.domain, key, value, proof)
+    }
+
+    /// Verifies a proof against a root hash without the tree. `None` proves that `key` is empty.
+    pub fn verify_with_root(
+        root: &Hash,
+        domain: HashDomain,
+        key: &Key,
+        value: Option<&Value>,
+        proof: &SparseMerkleProof,
+    ) -> bool {
+        let default_hashes = Self::compute_defaults(domain);
+        let mut calculated_hash = match value {
+            Some(value) => domain.hash_leaf::<H>(value),
+            None => default_hashes[DEPTH],
+        };
+        let mut merkle_hash = proof.hashes.iter();
+        for depth in (1..=DEPTH).rev() {
+            let sibling = if bit(&proof.bitmap, depth - 1) {
+                match merkle_hash.next() {
+                    Some(hash) => *hash,
+                    None => return false,
+                }
+            } else {
+                default_hashes[depth]
+            };
+            calculated_hash = if bit(key, depth - 1) {
+                domain.hash_pair::<H>(&sibling, &calculated_hash)
+            } else {
+                domain.hash_pair::<H>(&calculated_hash, &sibling)
+            };
+        }
+
+        merkle_hash.next().is_none() && calculated_hash == *root
+    }
+
+    /// Returns the hash of an empty subtree at every depth, from the root down to the leaves.
+    fn compute_defaults(domain: HashDomain) -> Vec<Hash> {
+        let mut default_hashes = vec![[0; 32]; DEPTH + 1];
+        for depth in (0..DEPTH).rev() {
+            let child = &default_hashes[depth + 1];
+            default_hashes[depth] = domain.hash_pair::<H>(child, child);
+        }
+        default_hashes
+    }
+
+    fn node_hash(&self, depth: usize, position: &Key) -> Hash {
+        match self.nodes.get(&(depth, *position)) {
+            Some(hash) => *hash,
+            None => self.default_hashes[depth],
+        }
+    }
+
+    /// Rehashes every node on the path from the parent of the leaf at `key` up to the root.
+    fn rehash_path(&mut self, key: &Key) {
+        // The position at `depth` is `key` with every bit from `depth` on cleared, so one bit is
+        // cleared per level on the way up
+        let mut position = *key;
+        for depth in (0..DEPTH).rev() {
+            clear_bit(&mut position, depth);
+            let mut right = position;
+            flip_bit(&mut right, depth);
+            let hash = self.domain.hash_pair::<H>(
+                &self.node_hash(depth + 1, &position),
+                &self.node_hash(depth + 1, &right),
+            );
+            if hash == self.default_hashes[depth] {
+                self.nodes.remove(&(depth, position));
+            } else {
+                self.nodes.insert((depth, position), hash);
+            }
+        }
+    }
+}
+
+/// Bit `i` of `key`, most significant first.
+fn bit(key: &Key, i: usize) -> bool {
+    (key[i / 8] >> (7 - i % 8)) & 1 == 1
+}
+
+fn flip_bit(key: &mut Key, i: usize) {
+    key[i / 8] ^= 1 << (7 - i % 8);
+}
+
+fn clear_bit(key: &mut Key, i: usize) {
+    key[i / 8] &= !(1 << (7 - i % 8));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn it_verifies_membership_and_non_membership() {
+        let mut tree = SparseMerkleTree::setup();
+        let keys = (0..10).map(|_| random_key()).collect::<Vec<_>>();
+        for key in keys.iter() {
+            tree.insert(*key, key.to_vec());
+        }
+
+        for key in keys.iter() {
+            let (value, proof) = tree.open(key);
+            assert_eq!(value.as_ref(), Some(&key.to_vec()));
+            assert!(tree.verify(key, value.as_ref(), &proof));
+            assert!(!tree.verify(key, None, &proof));
+        }
+
+        let key = random_key();
+        let (value, proof) = tree.open(&key);
+        assert_eq!(value, None);
+        assert!(tree.verify(&key, None, &proof));
+        assert!(!tree.verify(&key, Some(&key.to_vec()), &proof));
+    }
+
+    #[test]
+    fn it_updates_and_deletes() {
+        let mut tree = SparseMerkleTree::setup();
+        let empty_root = tree.root_hash();
+        let (first, second) = (random_key(), random_key());
+        tree.insert(first, vec![1]);
+        let root = tree.root_hash();
+
+        tree.insert(second, vec![2]);
+        assert_eq!(tree.update(second, vec![3]), Ok(vec![2]));
+        assert_eq!(
+            tree.update(random_key(), vec![3]),
+            Err(MerkleError::KeyNotFound)
+        );
+        let (value, proof) = tree.open(&second);
+        assert!(tree.verify(&second, value.as_ref(), &proof));
+
+        // Deleting restores the previous root and drops the nodes of the path
+        assert_eq!(tree.delete(&second), Some(vec![3]));
+        assert_eq!(tree.root_hash(), root);
+        assert_eq!(tree.nodes.len(), DEPTH + 1);
+        assert_eq!(tree.delete(&first), Some(vec![1]));
+        assert_eq!(tree.root_hash(), empty_root);
+        assert!(tree.nodes.is_empty());
+    }
+
+    #[test]
+    fn it_compresses_default_siblings() {
+        let mut tree = SparseMerkleTree::setup();
+        let mut keys = [[0; 32]; 3];
+        keys[1][31] = 1;
+        keys[2][0] = 0x80;
+        for key in keys.iter() {
+            tree.insert(*key, vec![0xff]);
+        }
+
+        // Key 0 has non-default siblings at the leaf and below the root only
+        let (value, proof) = tree.open(&keys[0]);
+        assert_eq!(proof.hashes.len(), 2);
+        assert!(bit(&proof.bitmap, 0) && bit(&proof.bitmap, DEPTH - 1));
+        assert!(tree.verify(&keys[0], value.as_ref(), &proof));
+
+        let mut short_proof = proof.clone();
+        short_proof.hashes.pop();
+        assert!(!tree.verify(&keys[0], value.as_ref(), &short_proof));
+        let mut long_proof = proof.clone();
+        long_proof.hashes.push([0; 32]);
+        assert!(!tree.verify(&keys[0], value.as_ref(), &long_proof));
+    }
+
+    #[test]
+    fn it_does_not_depend_on_insertion_order() {
+        let entries = (0..20)
+            .map(|_| (random_key(), random_key().to_vec()))
+            .collect::<Vec<_>>();
+        let mut tree = SparseMerkleTree::setup();
+        let mut reversed_tree = SparseMerkleTree::setup();
+        for (key, value) in entries.iter() {
+            tree.insert(*key, value.clone());
+        }
+        for (key, value) in entries.iter().rev() {
+            reversed_tree.insert(*key, value.clone());
+        }
+        assert_eq!(tree.root_hash(), reversed_tree.root_hash());
+    }
+
+    fn random_key() -> Key {
+        rand::thread_rng().gen()
+    }
+}
@@ -1,7 +1,13 @@
-use keccak_asm::{Digest, Keccak256};
+use std::marker::PhantomData;
 
-type Hash = [u8; 32];
-type Value = Vec<u8>;
+use crate::{
+    error::MerkleError,
+    hasher::{Keccak256, MerkleHasher},
+    verifier::{MerkleVerifier, TreeSize},
+};
+
+pub(crate) type Hash = [u8; 32];
+pub(crate) type Value = Vec<u8>;
 
 #[derive(Debug, Clone)]
 pub enum Node {
@@ -9,11 +15,84 @@ pub enum Node {
     Leaf { value: Option<Value>, hash: Hash },
 }
 
+/// How leaves and internal nodes are told apart when they are hashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashDomain {
+    /// Leaves are hashed as `Keccak256(value)` and internal nodes as `Keccak256(children)`, so a
+    /// leaf value of `32 * k` bytes can be passed off as an internal node and the other way round.
+    #[default]
+    Plain,
+    /// Leaves are hashed as `Keccak256(leaf || value)` and internal nodes as
+    /// `Keccak256(node || children)`.
+    Prefixed { leaf: u8, node: u8 },
+}
+
+impl HashDomain {
+    /// `0x00` for leaves and `0x01` for internal nodes, as in RFC 6962.
+    pub const SEPARATED: HashDomain = HashDomain::Prefixed {
+        leaf: 0x00,
+        node: 0x01,
+    };
+
+    pub(crate) fn leaf_hasher<H: MerkleHasher>(&self) -> H {
+        let mut hasher = H::new();
+        if let HashDomain::Prefixed { leaf, .. } = self {
+            hasher.update([*leaf]);
+        }
+        hasher
+    }
+
+    pub(crate) fn node_hasher<H: MerkleHasher>(&self) -> H {
+        let mut hasher = H::new();
+        if let HashDomain::Prefixed { node, .. } = self {
+            hasher.update([*node]);
+        }
+        hasher
+    }
+
+    pub(crate) fn hash_leaf<H: MerkleHasher>(&self, value: &Value) -> Hash {
+        let mut hasher = self.leaf_hasher::<H>();
+        hasher.update(value);
+        hasher.finalize()
+    }
+
+    pub(crate) fn hash_pair<H: MerkleHasher>(&self, left: &Hash, right: &Hash) -> Hash {
+        let mut hasher = self.node_hasher::<H>();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize()
+    }
+
+    /// Binds the number of leaves to the hash of the root node, see `Padding::EmptySubtree`.
+    pub(crate) fn hash_leaf_count<H: MerkleHasher>(&self, hash: &Hash, leaf_count: usize) -> Hash {
+        let mut hasher = self.node_hasher::<H>();
+        hasher.update(hash);
+        hasher.update((leaf_count as u64).to_be_bytes());
+        hasher.finalize()
+    }
+}
+
+/// How the missing children of the last node of a level are filled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    /// Repeats the hash of the last child, so `[a, b, c]` and `[a, b, c, c]` share a root.
+    #[default]
+    RepeatLast,
+    /// Fills in the hash of an empty subtree of the level, `[0; 32]` for a leaf and the hash of
+    /// `k` empty children above, and binds the number of leaves to the root hash.
+    EmptySubtree,
+}
+
+/// `k`-ary Merkle tree over byte values, hashed with `H`.
 #[derive(Debug)]
-pub struct MerkleTree {
+pub struct MerkleTree<H = Keccak256> {
     pub root: Option<Node>,
     pub height: Option<usize>,
+    pub leaf_count: Option<usize>,
     pub k: usize,
+    pub domain: HashDomain,
+    pub padding: Padding,
+    hasher: PhantomData<H>,
 }
 
 #[derive(Debug, Clone)]
@@ -21,20 +100,63 @@ pub struct MerkleProof {
     pub hashes: Vec<Hash>,
 }
 
+/// Proof for several leaves at once, laid out as in OpenZeppelin's `multiProofVerify` but with
+/// `k` children per node. Each internal node on the opened paths is hashed from `k` inputs, and
+/// the flag of each input tells whether it is taken from the already computed hashes (`true`) or
+/// from `hashes` (`false`), so siblings shared by several paths are sent once.
+#[derive(Debug, Clone)]
+pub struct MerkleMultiProof {
+    pub hashes: Vec<Hash>,
+    pub flags: Vec<bool>,
+}
+
 impl MerkleTree {
     pub fn setup(k: usize) -> Self {
-        Self {
+        Self::setup_with_domain(k, HashDomain::Plain)
+    }
+
+    pub fn setup_with_domain(k: usize, domain: HashDomain) -> Self {
+        Self::setup_with_options(k, domain, Padding::RepeatLast)
+    }
+
+    pub fn setup_with_options(k: usize, domain: HashDomain, padding: Padding) -> Self {
+        Self::setup_with_hasher(k, domain, padding).expect("Width must be at least 2")
+    }
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Sets up a tree hashed with `H`. Fails if `k` is below 2 or above `H::max_width(domain)`.
+    pub fn setup_with_hasher(
+        k: usize,
+        domain: HashDomain,
+        padding: Padding,
+    ) -> Result<Self, MerkleError> {
+        if k < 2 || H::max_width(domain).is_some_and(|max_width| k > max_width) {
+            return Err(MerkleError::InvalidWidth(k));
+        }
+        Ok(Self {
             root: None,
             height: None,
+            leaf_count: None,
             k,
-        }
+            domain,
+            padding,
+            hasher: PhantomData,
+        })
     }
 
-    pub fn commit(&mut self, values: &[&Value]) {
+    /// Commits to `values`. Fails if `values` is empty or a value is not a valid input of `H`.
+    pub fn commit(&mut self, values: &[&Value]) -> Result<(), MerkleError> {
+        if values.is_empty() {
+            return Err(MerkleError::EmptyVector);
+        }
+        if let Some(index) = values.iter().position(|value| !H::is_valid_input(value)) {
+            return Err(MerkleError::InvalidLeaf(index));
+        }
         let leaf_nodes: Vec<Node> = values
             .into_iter()
             .map(|value| {
-                let hash = Keccak256::digest(value).into();
+                let hash = self.hash_leaf(value);
                 Node::Leaf {
                     value: Some(value.to_vec()),
                     hash,
@@ -42,33 +164,73 @@ impl MerkleTree {
             })
             .collect();
 
-        self.root = Some(self.build_recursive(leaf_nodes));
-        self.height = Some(ceil_log_base(self.k, values.len()));
+        self.root = Some(self.build_recursive(leaf_nodes, 0));
+        self.height = Some(ceil_log_base(self.k, values.len()).max(1));
+        self.leaf_count = Some(values.len());
+        Ok(())
     }
 
     pub fn root_hash(&self) -> Option<[u8; 32]> {
-        match &self.root {
-            Some(Node::Internal { hash, .. }) | Some(Node::Leaf { hash, .. }) => Some(*hash),
-            None => None,
+        let hash = match self.root.as_ref()? {
+            Node::Internal { hash, .. } | Node::Leaf { hash, .. } => hash,
+        };
+        match self.padding {
+            Padding::RepeatLast => Some(*hash),
+            Padding::EmptySubtree => Some(self.domain.hash_leaf_count::<H>(hash, self.leaf_count?)),
         }
     }
 
-    fn build_recursive(&self, mut nodes: Vec<Node>) -> Node {
+    fn build_recursive(&self, mut nodes: Vec<Node>, level: usize) -> Node {
         if nodes.len() <= self.k {
-            let children = Self::extend_nodes(&nodes, self.k);
-            let hash = Self::hash_nodes(&children);
+            let children = self.pad_nodes(&nodes, level);
+            let hash = self.hash_nodes(&children);
             return Node::Internal { children, hash };
         }
 
         let mut parent_nodes = Vec::new();
         while !nodes.is_empty() {
             let chunk: Vec<Node> = nodes.drain(0..nodes.len().min(self.k)).collect();
-            let children = Self::extend_nodes(&chunk, self.k);
-            let hash = Self::hash_nodes(&children);
+            let children = self.pad_nodes(&chunk, level);
+            let hash = self.hash_nodes(&children);
             parent_nodes.push(Node::Internal { children, hash });
         }
 
-        self.build_recursive(parent_nodes)
+        self.build_recursive(parent_nodes, level + 1)
+    }
+
+    /// Pads a chunk of nodes at `level`, counted from the leaves, to `k` nodes.
+    fn pad_nodes(&self, nodes: &Vec<Node>, level: usize) -> Vec<Node> {
+        match self.padding {
+            Padding::RepeatLast => Self::extend_nodes(nodes, self.k),
+            Padding::EmptySubtree => {
+                let mut padded_nodes = nodes.clone();
+                if nodes.len() < self.k {
+                    let hash = self.empty_hash(level);
+                    let empty_node = match level {
+                        0 => Node::Leaf { value: None, hash },
+                        _ => Node::Internal {
+                            children: vec![],
+                            hash,
+                        },
+                    };
+                    padded_nodes.resize(self.k, empty_node);
+                }
+                padded_nodes
+            }
+        }
+    }
+
+    /// Returns the hash of an empty subtree at `level`, counted from the leaves.
+    fn empty_hash(&self, level: usize) -> Hash {
+        let mut hash = [0; 32];
+        for _ in 0..level {
+            let mut hasher = self.domain.node_hasher::<H>();
+            for _ in 0..self.k {
+                hasher.update(hash);
+            }
+            hash = hasher.finalize();
+        }
+        hash
     }
 
     fn extend_nodes(nodes: &Vec<Node>, k: usize) -> Vec<Node> {
@@ -106,8 +268,12 @@ impl MerkleTree {
         extended_nodes
     }
 
-    fn hash_nodes(nodes: &Vec<Node>) -> Hash {
-        let mut hasher = Keccak256::new();
+    fn hash_leaf(&self, value: &Value) -> Hash {
+        self.domain.hash_leaf::<H>(value)
+    }
+
+    fn hash_nodes(&self, nodes: &Vec<Node>) -> Hash {
+        let mut hasher = self.domain.node_hasher::<H>();
         for node in nodes {
             match node {
                 Node::Internal { hash, .. } | Node::Leaf { hash, .. } => {
@@ -115,10 +281,101 @@ impl MerkleTree {
                 }
             }
         }
-        hasher.finalize().into()
+        hasher.finalize()
     }
 
-    pub fn open(&self, index: usize) -> (Value, MerkleProof) {
+    /// Replaces the value at `index` and rehashes its path, giving the root of a fresh commit.
+    pub fn update(&mut self, index: usize, value: Value) -> Result<(), MerkleError> {
+        self.update_many(vec![(index, value)])
+    }
+
+    /// Replaces several values and rehashes every node on their paths once. A later update of
+    /// the same index wins.
+    pub fn update_many(&mut self, mut updates: Vec<(usize, Value)>) -> Result<(), MerkleError> {
+        let leaf_count = self.leaf_count.ok_or(MerkleError::NotCommitted)?;
+        if let Some(&(index, _)) = updates.iter().find(|(index, _)| *index >= leaf_count) {
+            return Err(MerkleError::IndexOutOfRange(index));
+        }
+        if let Some(&(index, _)) = updates.iter().find(|(_, value)| !H::is_valid_input(value)) {
+            return Err(MerkleError::InvalidLeaf(index));
+        }
+        if updates.is_empty() {
+            return Ok(());
+        }
+        updates.sort_by_key(|(index, _)| *index);
+
+        let mut root = self.root.take().unwrap();
+        self.update_node(&mut root, 0, 0, &updates);
+        self.root = Some(root);
+        Ok(())
+    }
+
+    /// Applies `updates`, sorted by index, to the subtree of the node at `depth` and `position`.
+    fn update_node(
+        &self,
+        node: &mut Node,
+        depth: usize,
+        position: usize,
+        updates: &[(usize, Value)],
+    ) {
+        match node {
+            Node::Leaf { value, hash } => {
+                let (_, new_value) = updates.last().unwrap();
+                *hash = self.hash_leaf(new_value);
+                *value = Some(new_value.clone());
+            }
+            Node::Internal { children, hash } => {
+                // Number of leaves below each child
+                let child_span = self.k.pow((self.height.unwrap() - depth - 1) as u32);
+                let first_leaf = position * self.k * child_span;
+                let mut updates = updates;
+                while let Some(&(index, _)) = updates.first() {
+                    let slot = (index - first_leaf) / child_span;
+                    let count = updates
+                        .partition_point(|(index, _)| (index - first_leaf) / child_span == slot);
+                    let (slot_updates, rest) = updates.split_at(count);
+                    self.update_node(
+                        &mut children[slot],
+                        depth + 1,
+                        position * self.k + slot,
+                        slot_updates,
+                    );
+                    updates = rest;
+                }
+
+                // Padded children repeat the hash of the last child, which may have changed
+                if self.padding == Padding::RepeatLast {
+                    let child_count = (self.level_size(depth + 1) - position * self.k).min(self.k);
+                    let last_hash = match &children[child_count - 1] {
+                        Node::Internal { hash, .. } | Node::Leaf { hash, .. } => *hash,
+                    };
+                    for child in children[child_count..].iter_mut() {
+                        match child {
+                            Node::Internal { hash, .. } | Node::Leaf { hash, .. } => {
+                                *hash = last_hash
+                            }
+                        }
+                    }
+                }
+                *hash = self.hash_nodes(children);
+            }
+        }
+    }
+
+    /// Returns the number of nodes at `depth` that are not padding.
+    fn level_size(&self, depth: usize) -> usize {
+        let mut size = self.leaf_count.unwrap();
+        for _ in depth..self.height.unwrap() {
+            size = size.div_ceil(self.k);
+        }
+        size
+    }
+
+    pub fn open(&self, index: usize) -> Result<(Value, MerkleProof), MerkleError> {
+        let leaf_count = self.leaf_count.ok_or(MerkleError::NotCommitted)?;
+        if index >= leaf_count {
+            return Err(MerkleError::IndexOutOfRange(index));
+        }
         let tree_path = Self::compute_path(index, self.height.unwrap(), self.k);
         let mut merkle_hashes: Vec<Hash> = Vec::new();
         let mut current_node = self.root.as_ref().unwrap();
@@ -149,15 +406,95 @@ impl MerkleTree {
 
         merkle_hashes.reverse();
 
-        (
+        Ok((
             tree_value,
             MerkleProof {
                 hashes: merkle_hashes,
             },
-        )
+        ))
+    }
+
+    /// Opens the values at `indices`, which must be strictly increasing, with a single proof.
+    pub fn open_many(
+        &self,
+        indices: &[usize],
+    ) -> Result<(Vec<Value>, MerkleMultiProof), MerkleError> {
+        let leaf_count = self.leaf_count.ok_or(MerkleError::NotCommitted)?;
+        if let Some(&index) = indices.iter().find(|&&index| index >= leaf_count) {
+            return Err(MerkleError::IndexOutOfRange(index));
+        }
+        let height = self.height.unwrap();
+        let steps =
+            Self::multiproof_steps(indices, height, self.k).ok_or(MerkleError::InvalidIndices)?;
+
+        let mut hashes: Vec<Hash> = Vec::new();
+        let mut flags: Vec<bool> = Vec::new();
+        for (depth, position, step_flags) in steps {
+            let children = match self.node_at(depth, position) {
+                Node::Internal { children, .. } => children,
+                _ => panic!("Invalid node type"),
+            };
+            for (child_node, &flag) in children.iter().zip(step_flags.iter()) {
+                if !flag {
+                    match child_node {
+                        Node::Internal { hash, .. } | Node::Leaf { hash, .. } => hashes.push(*hash),
+                    }
+                }
+            }
+            flags.extend(step_flags);
+        }
+
+        let values = indices
+            .iter()
+            .map(|&index| match self.node_at(height, index) {
+                Node::Leaf { value, .. } => value.as_ref().unwrap().clone(),
+                _ => panic!("Invalid node type"),
+            })
+            .collect();
+
+        Ok((values, MerkleMultiProof { hashes, flags }))
+    }
+
+    fn node_at(&self, depth: usize, position: usize) -> &Node {
+        let mut current_node = self.root.as_ref().unwrap();
+        for path_index in Self::compute_path(position, depth, self.k) {
+            match current_node {
+                Node::Internal { children, .. } => current_node = &children[path_index],
+                _ => panic!("Invalid node type"),
+            }
+        }
+        current_node
+    }
+
+    /// Internal nodes hashed when verifying a multiproof of `indices`, from the leaves up, as
+    /// `(depth, position, flags)` where `flags[i]` is set if child `i` is on an opened path.
+    /// Returns `None` if `indices` is empty or not strictly increasing.
+    pub(crate) fn multiproof_steps(
+        indices: &[usize],
+        height: usize,
+        width: usize,
+    ) -> Option<Vec<(usize, usize, Vec<bool>)>> {
+        if indices.is_empty() || indices.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return None;
+        }
+        let mut steps = Vec::new();
+        let mut positions = indices.to_vec();
+        for depth in (0..height).rev() {
+            let mut parents: Vec<usize> = Vec::new();
+            for position in positions {
+                let parent = position / width;
+                if parents.last() != Some(&parent) {
+                    parents.push(parent);
+                    steps.push((depth, parent, vec![false; width]));
+                }
+                steps.last_mut().unwrap().2[position % width] = true;
+            }
+            positions = parents;
+        }
+        Some(steps)
     }
 
-    fn compute_path(index: usize, height: usize, width: usize) -> Vec<usize> {
+    pub(crate) fn compute_path(index: usize, height: usize, width: usize) -> Vec<usize> {
         let mut n = index.clone();
         let mut path = vec![0; height];
         for i in (0..height).rev() {
@@ -171,24 +508,33 @@ impl MerkleTree {
     }
 
     pub fn verify(&self, index: usize, value: &Value, proof: &MerkleProof) -> bool {
-        let mut calculated_hash: Hash = Keccak256::digest(value).into();
-        let mut path_index = Self::compute_path(index, self.height.unwrap(), self.k);
-        let mut merkle_hash = proof.hashes.iter();
-        path_index.reverse();
-
-        for merkle_hash_index in path_index {
-            let mut hasher = Keccak256::new();
-            for branch_index in 0..self.k {
-                if branch_index == merkle_hash_index {
-                    hasher.update(calculated_hash);
-                } else {
-                    hasher.update(merkle_hash.next().unwrap());
-                }
-            }
-            calculated_hash = hasher.finalize().into();
+        match self.verifier() {
+            Some(verifier) => verifier.verify(index, value, proof),
+            None => false,
         }
+    }
+
+    pub fn verify_many(
+        &self,
+        indices: &[usize],
+        values: &[Value],
+        proof: &MerkleMultiProof,
+    ) -> bool {
+        match self.verifier() {
+            Some(verifier) => verifier.verify_many(indices, values, proof),
+            None => false,
+        }
+    }
 
-        calculated_hash == self.root_hash().unwrap()
+    /// Returns a verifier for the committed tree that does not need the tree itself.
+    pub fn verifier(&self) -> Option<MerkleVerifier<H>> {
+        Some(MerkleVerifier::with_hasher(
+            self.root_hash()?,
+            self.k,
+            TreeSize::LeafCount(self.leaf_count?),
+            self.domain,
+            self.padding,
+        ))
     }
 
     pub fn print_tree(&self) {
@@ -225,9 +571,14 @@ pub fn ceil_log_base(base: usize, n: usize) -> usize {
         panic!("Invalid base or n")
     }
 
-    let base_f64 = base as f64;
-    let n_f64 = n as f64;
-    n_f64.log(base_f64).ceil() as usize
+    // Counts in integers, the float logarithm of an exact power such as 5^3 can round up
+    let mut height = 0;
+    let mut capacity = 1usize;
+    while capacity < n {
+        capacity = capacity.saturating_mul(base);
+        height += 1;
+    }
+    height
 }
 
 #[cfg(test)]
@@ -235,6 +586,7 @@ mod tests {
     use std::vec;
 
     use super::*;
+    use crate::hasher::{Blake3, Sha256};
     use rand::Rng;
 
     #[test]
@@ -251,7 +603,7 @@ mod tests {
         let mut tree = MerkleTree::setup(width);
         let vec = vec![random_bytes(), random_bytes(), random_bytes()];
         let vec = vec.iter().collect::<Vec<_>>();
-        tree.commit(vec.as_slice());
+        tree.commit(vec.as_slice()).unwrap();
         tree.print_tree();
 
         assert_eq!(tree.height.unwrap(), 2);
@@ -262,7 +614,7 @@ mod tests {
         let width = 2;
         let height = 2;
         let index = 1;
-        let tree_path = MerkleTree::compute_path(index, height, width);
+        let tree_path = <MerkleTree>::compute_path(index, height, width);
         println!("Tree path: {:?}", tree_path);
     }
 
@@ -272,9 +624,9 @@ mod tests {
         let mut tree = MerkleTree::setup(width);
         let vec = vec![random_bytes(), random_bytes(), random_bytes()];
         let vec = vec.iter().collect::<Vec<_>>();
-        tree.commit(vec.as_slice());
+        tree.commit(vec.as_slice()).unwrap();
 
-        let (value, _) = tree.open(1);
+        let (value, _) = tree.open(1).unwrap();
         assert_eq!(value, vec[1].clone());
     }
 
@@ -284,9 +636,9 @@ mod tests {
         let mut tree = MerkleTree::setup(width);
         let vec = vec![random_bytes(), random_bytes(), random_bytes()];
         let vec = vec.iter().collect::<Vec<_>>();
-        tree.commit(vec.as_slice());
+        tree.commit(vec.as_slice()).unwrap();
 
-        let (value, proof) = tree.open(1);
+        let (value, proof) = tree.open(1).unwrap();
         assert!(tree.verify(1, &value, &proof));
     }
 
@@ -296,9 +648,9 @@ mod tests {
         let mut tree = MerkleTree::setup(width);
         let vec = vec![random_bytes(), random_bytes(), random_bytes()];
         let vec = vec.iter().collect::<Vec<_>>();
-        tree.commit(vec.as_slice());
+        tree.commit(vec.as_slice()).unwrap();
 
-        let (value, proof) = tree.open(1);
+        let (value, proof) = tree.open(1).unwrap();
         assert_eq!(tree.verify(0, &value, &proof), false);
     }
 
@@ -309,11 +661,290 @@ mod tests {
         let mut tree = MerkleTree::setup(width);
         let vec = (0..vec_length).map(|_| random_bytes()).collect::<Vec<_>>();
         let vec = vec.iter().collect::<Vec<_>>();
-        tree.commit(vec.as_slice());
+        tree.commit(vec.as_slice()).unwrap();
+
+        let random_index = rand::thread_rng().gen_range(0..vec_length);
+        let (value, proof) = tree.open(random_index).unwrap();
+        assert!(tree.verify(random_index, &value, &proof));
+    }
+
+    #[test]
+    fn it_verifies_with_domain_separation() {
+        let width = 4;
+        let vec_length = 100;
+        let mut tree = MerkleTree::setup_with_domain(width, HashDomain::SEPARATED);
+        let vec = (0..vec_length).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
 
         let random_index = rand::thread_rng().gen_range(0..vec_length);
-        let (value, proof) = tree.open(random_index);
+        let (value, proof) = tree.open(random_index).unwrap();
         assert!(tree.verify(random_index, &value, &proof));
+
+        let mut plain_tree = MerkleTree::setup(width);
+        plain_tree.commit(vec.as_slice()).unwrap();
+        assert_ne!(tree.root_hash(), plain_tree.root_hash());
+    }
+
+    #[test]
+    fn it_prevents_second_preimage_with_domain_separation() {
+        let width = 2;
+        let vec = (0..4).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+
+        for domain in [HashDomain::Plain, HashDomain::SEPARATED] {
+            let mut tree = MerkleTree::setup_with_domain(width, domain);
+            tree.commit(vec.as_slice()).unwrap();
+
+            // Leaves that are the encodings of the internal nodes above the original leaves
+            let forged_vec = vec
+                .chunks(width)
+                .map(|chunk| {
+                    let mut value = tree.hash_leaf(chunk[0]).to_vec();
+                    value.extend(tree.hash_leaf(chunk[1]));
+                    value
+                })
+                .collect::<Vec<_>>();
+            let forged_vec = forged_vec.iter().collect::<Vec<_>>();
+            let mut forged_tree = MerkleTree::setup_with_domain(width, domain);
+            forged_tree.commit(forged_vec.as_slice()).unwrap();
+
+            let (value, proof) = forged_tree.open(0).unwrap();
+            assert!(forged_tree.verify(0, &value, &proof));
+            let same_root = forged_tree.root_hash() == tree.root_hash();
+            assert_eq!(same_root, domain == HashDomain::Plain);
+        }
+    }
+
+    #[test]
+    fn it_distinguishes_padded_vectors() {
+        let width = 4;
+        let vec = (0..3).map(|_| random_bytes()).collect::<Vec<_>>();
+        let mut padded_vec = vec.clone();
+        padded_vec.push(vec[2].clone());
+        let vec = vec.iter().collect::<Vec<_>>();
+        let padded_vec = padded_vec.iter().collect::<Vec<_>>();
+
+        let mut tree = MerkleTree::setup(width);
+        tree.commit(vec.as_slice()).unwrap();
+        let mut padded_tree = MerkleTree::setup(width);
+        padded_tree.commit(padded_vec.as_slice()).unwrap();
+        assert_eq!(tree.root_hash(), padded_tree.root_hash());
+
+        let mut tree =
+            MerkleTree::setup_with_options(width, HashDomain::SEPARATED, Padding::EmptySubtree);
+        tree.commit(vec.as_slice()).unwrap();
+        let mut padded_tree =
+            MerkleTree::setup_with_options(width, HashDomain::SEPARATED, Padding::EmptySubtree);
+        padded_tree.commit(padded_vec.as_slice()).unwrap();
+        assert_ne!(tree.root_hash(), padded_tree.root_hash());
+    }
+
+    #[test]
+    fn it_verifies_with_empty_subtree_padding() {
+        let width = 3;
+        let vec_length = 50;
+        let vec = (0..vec_length).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        let mut tree =
+            MerkleTree::setup_with_options(width, HashDomain::SEPARATED, Padding::EmptySubtree);
+        tree.commit(vec.as_slice()).unwrap();
+
+        for index in [0, 26, vec_length - 1] {
+            let (value, proof) = tree.open(index).unwrap();
+            assert_eq!(&value, vec[index]);
+            assert!(tree.verify(index, &value, &proof));
+        }
+
+        // Padded slots cannot be proven
+        let (_, proof) = tree.open(vec_length - 1).unwrap();
+        assert!(!tree.verify(vec_length, &vec![0u8; 32], &proof));
+    }
+
+    #[test]
+    fn it_does_not_open_index_out_of_range() {
+        let width = 4;
+        let mut tree = MerkleTree::setup(width);
+        assert_eq!(tree.open(0).unwrap_err(), MerkleError::NotCommitted);
+
+        let vec = (0..3).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+        assert_eq!(tree.open(3).unwrap_err(), MerkleError::IndexOutOfRange(3));
+        assert_eq!(tree.open(16).unwrap_err(), MerkleError::IndexOutOfRange(16));
+    }
+
+    #[test]
+    fn it_does_not_commit_empty_vector() {
+        let mut tree = MerkleTree::setup(4);
+        assert_eq!(tree.commit(&[]).unwrap_err(), MerkleError::EmptyVector);
+        assert_eq!(tree.root_hash(), None);
+    }
+
+    #[test]
+    fn it_opens_exact_powers_of_width() {
+        for (width, vec_length, height) in [(5, 125, 3), (6, 216, 3), (10, 1000, 3), (2, 64, 6)] {
+            let mut tree = MerkleTree::setup(width);
+            let vec = (0..vec_length).map(|_| random_bytes()).collect::<Vec<_>>();
+            let vec = vec.iter().collect::<Vec<_>>();
+            tree.commit(vec.as_slice()).unwrap();
+            assert_eq!(tree.height, Some(height));
+
+            for index in [0, 7, vec_length - 1] {
+                let (value, proof) = tree.open(index).unwrap();
+                assert!(tree.verify(index, &value, &proof));
+            }
+            let indices = [0, 7, vec_length - 1];
+            let (values, proof) = tree.open_many(&indices).unwrap();
+            assert!(tree.verify_many(&indices, &values, &proof));
+        }
+        assert_eq!(ceil_log_base(2, 1 << 29), 29);
+        assert_eq!(ceil_log_base(5, 126), 4);
+    }
+
+    #[test]
+    fn it_opens_single_value() {
+        let mut tree = MerkleTree::setup(2);
+        let vec = [random_bytes()];
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let (value, proof) = tree.open(0).unwrap();
+        assert!(tree.verify(0, &value, &proof));
+    }
+
+    #[test]
+    fn it_verifies_multiproof() {
+        let width = 4;
+        let mut tree = MerkleTree::setup(width);
+        let vec = (0..50).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let indices = [2, 3, 17, 40, 49];
+        let (values, proof) = tree.open_many(&indices).unwrap();
+        assert_eq!(values, indices.map(|index| vec[index].clone()));
+        assert!(tree.verify_many(&indices, &values, &proof));
+
+        // Shared siblings are sent once
+        let single_proof_hashes = indices.len() * 3 * (width - 1);
+        assert!(proof.hashes.len() < single_proof_hashes);
+
+        let mut false_values = values.clone();
+        false_values.swap(0, 1);
+        assert!(!tree.verify_many(&indices, &false_values, &proof));
+    }
+
+    #[test]
+    fn it_does_not_open_many_invalid_indices() {
+        let mut tree = MerkleTree::setup(2);
+        let vec = (0..5).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+
+        assert_eq!(
+            tree.open_many(&[]).unwrap_err(),
+            MerkleError::InvalidIndices
+        );
+        assert_eq!(
+            tree.open_many(&[3, 1]).unwrap_err(),
+            MerkleError::InvalidIndices
+        );
+        assert_eq!(
+            tree.open_many(&[1, 1]).unwrap_err(),
+            MerkleError::InvalidIndices
+        );
+        assert_eq!(
+            tree.open_many(&[1, 5]).unwrap_err(),
+            MerkleError::IndexOutOfRange(5)
+        );
+    }
+
+    #[test]
+    fn it_verifies_with_hashers() {
+        fn commit<H: MerkleHasher>(vec: &[&Value]) -> MerkleTree<H> {
+            let mut tree =
+                MerkleTree::setup_with_hasher(3, HashDomain::SEPARATED, Padding::EmptySubtree)
+                    .unwrap();
+            tree.commit(vec).unwrap();
+            tree
+        }
+        let vec = (0..20).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        let keccak_tree = commit::<Keccak256>(&vec);
+        let sha256_tree = commit::<Sha256>(&vec);
+        let blake3_tree = commit::<Blake3>(&vec);
+
+        let (value, proof) = sha256_tree.open(11).unwrap();
+        assert!(sha256_tree.verify(11, &value, &proof));
+        assert!(!keccak_tree.verify(11, &value, &proof));
+        let (value, proof) = blake3_tree.open(11).unwrap();
+        assert!(blake3_tree.verify(11, &value, &proof));
+        assert!(!sha256_tree.verify(11, &value, &proof));
+        assert_ne!(keccak_tree.root_hash(), blake3_tree.root_hash());
+    }
+
+    #[test]
+    fn it_updates_like_fresh_commit() {
+        let sizes = [(3, 23), (5, 125), (6, 216), (2, 64)];
+        for ((width, vec_length), padding) in sizes
+            .into_iter()
+            .flat_map(|size| [(size, Padding::RepeatLast), (size, Padding::EmptySubtree)])
+        {
+            let mut vec = (0..vec_length).map(|_| random_bytes()).collect::<Vec<_>>();
+            let mut tree = MerkleTree::setup_with_options(width, HashDomain::SEPARATED, padding);
+            tree.commit(vec.iter().collect::<Vec<_>>().as_slice())
+                .unwrap();
+
+            // The last value is repeated into the padding of every level above it
+            let last = vec_length - 1;
+            for index in [0, 13, last] {
+                vec[index] = random_bytes();
+                tree.update(index, vec[index].clone()).unwrap();
+            }
+            let updates = [
+                (4, random_bytes()),
+                (1, random_bytes()),
+                (7, random_bytes()),
+            ];
+            for (index, value) in updates.iter() {
+                vec[*index] = value.clone();
+            }
+            tree.update_many(updates.to_vec()).unwrap();
+
+            let mut fresh_tree =
+                MerkleTree::setup_with_options(width, HashDomain::SEPARATED, padding);
+            fresh_tree
+                .commit(vec.iter().collect::<Vec<_>>().as_slice())
+                .unwrap();
+            assert_eq!(tree.root_hash(), fresh_tree.root_hash());
+
+            for index in [7, last] {
+                let (value, proof) = tree.open(index).unwrap();
+                assert_eq!(value, vec[index]);
+                assert!(tree.verify(index, &value, &proof));
+            }
+        }
+    }
+
+    #[test]
+    fn it_does_not_update_index_out_of_range() {
+        let mut tree = MerkleTree::setup(2);
+        assert_eq!(
+            tree.update(0, random_bytes()).unwrap_err(),
+            MerkleError::NotCommitted
+        );
+
+        let vec = (0..5).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+        let root = tree.root_hash();
+        assert_eq!(
+            tree.update_many(vec![(1, random_bytes()), (5, random_bytes())])
+                .unwrap_err(),
+            MerkleError::IndexOutOfRange(5)
+        );
+        assert_eq!(tree.root_hash(), root);
     }
 
     fn random_bytes() -> Value {
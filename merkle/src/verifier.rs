@@ -0,0 +1,360 @@
+use std::{collections::VecDeque, marker::PhantomData};
+
+use crate::{
+    hasher::{Keccak256, MerkleHasher},
+    tree::{
+        ceil_log_base, Hash, HashDomain, MerkleMultiProof, MerkleProof, MerkleTree, Padding, Value,
+    },
+};
+
+/// Size of the tree a proof is verified against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeSize {
+    /// Number of committed leaves. Indices past the last leaf are rejected.
+    LeafCount(usize),
+    /// Number of levels below the root. Every index of the `width^height` slots is accepted, as
+    /// in `MerkleVerifier.sol`, but larger indices are rejected where the contract reduces them
+    /// modulo `width^height`.
+    Height(usize),
+}
+
+/// Verifies `MerkleProof`s against a root hash without the tree, with the same semantics as
+/// `MerkleVerifier.sol::verify` except that indices past the `width^height` slots are rejected
+/// instead of aliasing a slot. A root with `Padding::EmptySubtree` binds the number of leaves, so
+/// it can only be verified with `TreeSize::LeafCount`. Nothing verifies for a width below 2 or
+/// above `H::max_width`, or for values and hashes that are not valid inputs of `H`.
+#[derive(Debug, Clone)]
+pub struct MerkleVerifier<H = Keccak256> {
+    pub root: Hash,
+    pub width: usize,
+    pub size: TreeSize,
+    pub domain: HashDomain,
+    pub padding: Padding,
+    hasher: PhantomData<H>,
+}
+
+impl MerkleVerifier {
+    pub fn new(root: Hash, width: usize, size: TreeSize) -> Self {
+        Self::new_with_options(root, width, size, HashDomain::Plain, Padding::RepeatLast)
+    }
+
+    pub fn new_with_options(
+        root: Hash,
+        width: usize,
+        size: TreeSize,
+        domain: HashDomain,
+        padding: Padding,
+    ) -> Self {
+        Self::with_hasher(root, width, size, domain, padding)
+    }
+}
+
+impl<H: MerkleHasher> MerkleVerifier<H> {
+    pub fn with_hasher(
+        root: Hash,
+        width: usize,
+        size: TreeSize,
+        domain: HashDomain,
+        padding: Padding,
+    ) -> Self {
+        Self {
+            root,
+            width,
+            size,
+            domain,
+            padding,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn verify(&self, index: usize, value: &Value, proof: &MerkleProof) -> bool {
+        let Some((height, leaf_count)) = self.dimensions() else {
+            return false;
+        };
+        if !self.in_range(index, height, leaf_count)
+            || proof.hashes.len() != height * (self.width - 1)
+            || !H::is_valid_input(value)
+            || !proof.hashes.iter().all(|hash| H::is_valid_input(hash))
+        {
+            return false;
+        }
+
+        let mut calculated_hash: Hash = self.domain.hash_leaf::<H>(value);
+        let mut path_index = MerkleTree::<H>::compute_path(index, height, self.width);
+        let mut merkle_hash = proof.hashes.iter();
+        path_index.reverse();
+
+        for merkle_hash_index in path_index {
+            let mut hasher = self.domain.node_hasher::<H>();
+            for branch_index in 0..self.width {
+                if branch_index == merkle_hash_index {
+                    hasher.update(calculated_hash);
+                } else {
+                    hasher.update(merkle_hash.next().unwrap());
+                }
+            }
+            calculated_hash = hasher.finalize();
+        }
+
+        self.matches_root(calculated_hash, leaf_count)
+    }
+
+    /// Verifies a `MerkleMultiProof` by hashing the opened values up to the root in the order
+    /// given by its flags, as `MerkleProof.sol::multiProofVerify` does. The flags are checked
+    /// against `indices`, which must be strictly increasing, so each value is bound to its index.
+    pub fn verify_many(
+        &self,
+        indices: &[usize],
+        values: &[Value],
+        proof: &MerkleMultiProof,
+    ) -> bool {
+        let Some((height, leaf_count)) = self.dimensions() else {
+            return false;
+        };
+        if indices.len() != values.len()
+            || !indices
+                .iter()
+                .all(|&index| self.in_range(index, height, leaf_count))
+            || !values.iter().all(|value| H::is_valid_input(value))
+            || !proof.hashes.iter().all(|hash| H::is_valid_input(hash))
+        {
+            return false;
+        }
+        let Some(steps) = MerkleTree::<H>::multiproof_steps(indices, height, self.width) else {
+            return false;
+        };
+        if !proof
+            .flags
+            .iter()
+            .eq(steps.iter().flat_map(|(_, _, flags)| flags))
+        {
+            return false;
+        }
+
+        let mut queue: VecDeque<Hash> = values
+            .iter()
+            .map(|value| self.domain.hash_leaf::<H>(value))
+            .collect();
+        let mut merkle_hash = proof.hashes.iter();
+        for flags in proof.flags.chunks(self.width) {
+            let mut hasher = self.domain.node_hasher::<H>();
+            for &flag in flags {
+                let input = if flag {
+                    queue.pop_front()
+                } else {
+                    merkle_hash.next().copied()
+                };
+                let Some(input) = input else {
+                    return false;
+                };
+                hasher.update(input);
+            }
+            queue.push_back(hasher.finalize());
+        }
+        if merkle_hash.next().is_some() || queue.len() != 1 {
+            return false;
+        }
+
+        self.matches_root(queue.pop_front().unwrap(), leaf_count)
+    }
+
+    /// Height of the tree and, if known, its number of leaves.
+    fn dimensions(&self) -> Option<(usize, Option<usize>)> {
+        if self.width < 2
+            || H::max_width(self.domain).is_some_and(|max_width| self.width > max_width)
+        {
+            return None;
+        }
+        match self.size {
+            TreeSize::LeafCount(0) => None,
+            TreeSize::LeafCount(leaf_count) => Some((
+                ceil_log_base(self.width, leaf_count).max(1),
+                Some(leaf_count),
+            )),
+            TreeSize::Height(height) => Some((height, None)),
+        }
+    }
+
+    fn in_range(&self, index: usize, height: usize, leaf_count: Option<usize>) -> bool {
+        match leaf_count {
+            Some(leaf_count) => index < leaf_count,
+            None => match self.width.checked_pow(height as u32) {
+                Some(size) => index < size,
+                None => true,
+            },
+        }
+    }
+
+    fn matches_root(&self, mut calculated_hash: Hash, leaf_count: Option<usize>) -> bool {
+        if self.padding == Padding::EmptySubtree {
+            let Some(leaf_count) = leaf_count else {
+                return false;
+            };
+            calculated_hash = self
+                .domain
+                .hash_leaf_count::<H>(&calculated_hash, leaf_count);
+        }
+
+        calculated_hash == self.root
+    }
+}
+
+/// Verifies a proof against a root committed with the default options of `MerkleTree::setup`.
+pub fn verify_proof(
+    root: &Hash,
+    width: usize,
+    size: TreeSize,
+    index: usize,
+    value: &Value,
+    proof: &MerkleProof,
+) -> bool {
+    MerkleVerifier::new(*root, width, size).verify(index, value, proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn random_bytes() -> Value {
+        let mut rng = rand::thread_rng();
+        (0..32).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn it_verifies_without_tree() {
+        let width = 4;
+        let vec_length = 50;
+        let mut tree = MerkleTree::setup(width);
+        let vec = (0..vec_length).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+        let root = tree.root_hash().unwrap();
+        let (value, proof) = tree.open(37).unwrap();
+        drop(tree);
+
+        let verify = |size, index| verify_proof(&root, width, size, index, &value, &proof);
+        assert!(verify(TreeSize::LeafCount(vec_length), 37));
+        assert!(verify(TreeSize::Height(3), 37));
+        assert!(!verify(TreeSize::Height(3), 36));
+        assert!(!verify(TreeSize::LeafCount(37), 37));
+    }
+
+    #[test]
+    fn it_verifies_exact_powers_of_width() {
+        for (width, vec_length, height) in [(5, 125, 3), (6, 216, 3), (2, 64, 6)] {
+            let mut tree = MerkleTree::setup(width);
+            let vec = (0..vec_length).map(|_| random_bytes()).collect::<Vec<_>>();
+            let vec = vec.iter().collect::<Vec<_>>();
+            tree.commit(vec.as_slice()).unwrap();
+            let root = tree.root_hash().unwrap();
+            let (value, proof) = tree.open(7).unwrap();
+
+            let verify = |size| verify_proof(&root, width, size, 7, &value, &proof);
+            assert!(verify(TreeSize::LeafCount(vec_length)));
+            assert!(verify(TreeSize::Height(height)));
+            assert!(!verify(TreeSize::Height(height + 1)));
+        }
+    }
+
+    #[test]
+    fn it_rejects_width_below_two() {
+        let mut tree = MerkleTree::setup(2);
+        let vec = (0..4).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+        let root = tree.root_hash().unwrap();
+        let (value, proof) = tree.open(1).unwrap();
+
+        for width in [0, 1] {
+            assert!(!verify_proof(
+                &root,
+                width,
+                TreeSize::LeafCount(4),
+                1,
+                &value,
+                &proof
+            ));
+            assert!(!verify_proof(
+                &root,
+                width,
+                TreeSize::Height(2),
+                1,
+                &value,
+                &proof
+            ));
+            let verifier = MerkleVerifier::new(root, width, TreeSize::LeafCount(4));
+            assert!(!verifier.verify_many(
+                &[1],
+                std::slice::from_ref(&value),
+                &MerkleMultiProof {
+                    hashes: proof.hashes.clone(),
+                    flags: vec![false, true, true, false],
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn it_rejects_proof_of_wrong_length() {
+        let width = 4;
+        let mut tree = MerkleTree::setup(width);
+        let vec = (0..20).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+        let verifier = tree.verifier().unwrap();
+
+        let (value, mut proof) = tree.open(5).unwrap();
+        proof.hashes.pop();
+        assert!(!verifier.verify(5, &value, &proof));
+        let verifier = MerkleVerifier::new(verifier.root, width, TreeSize::Height(1));
+        assert!(!verifier.verify(5, &value, &proof));
+    }
+
+    #[test]
+    fn it_verifies_with_options() {
+        let width = 3;
+        let mut tree =
+            MerkleTree::setup_with_options(width, HashDomain::SEPARATED, Padding::EmptySubtree);
+        let vec = (0..10).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+        let (value, proof) = tree.open(9).unwrap();
+
+        let verifier = tree.verifier().unwrap();
+        assert!(verifier.verify(9, &value, &proof));
+
+        // The root binds the leaf count, which a height does not give
+        let verifier = MerkleVerifier {
+            size: TreeSize::Height(3),
+            ..verifier
+        };
+        assert!(!verifier.verify(9, &value, &proof));
+    }
+
+    #[test]
+    fn it_verifies_multiproof_without_tree() {
+        let width = 3;
+        let mut tree =
+            MerkleTree::setup_with_options(width, HashDomain::SEPARATED, Padding::EmptySubtree);
+        let vec = (0..25).map(|_| random_bytes()).collect::<Vec<_>>();
+        let vec = vec.iter().collect::<Vec<_>>();
+        tree.commit(vec.as_slice()).unwrap();
+        let indices = [0, 1, 13, 24];
+        let (values, proof) = tree.open_many(&indices).unwrap();
+        let verifier = tree.verifier().unwrap();
+        drop(tree);
+
+        assert!(verifier.verify_many(&indices, &values, &proof));
+        assert!(!verifier.verify_many(&[0, 1, 13, 23], &values, &proof));
+        assert!(!verifier.verify_many(&[1, 0, 13, 24], &values, &proof));
+
+        // Dropping a hash or a flag does not verify
+        let mut short_proof = proof.clone();
+        short_proof.hashes.pop();
+        assert!(!verifier.verify_many(&indices, &values, &short_proof));
+        let mut short_proof = proof.clone();
+        short_proof.flags.pop();
+        assert!(!verifier.verify_many(&indices, &values, &short_proof));
+    }
+}
@@ -8,6 +8,7 @@ fi
 
 WIDTHS=2,4,8,32,64,256
 SIZES=10,100,1000,5000,10000,50000,100000,500000,1000000,5000000
+HASHERS=keccak256,sha256,blake3
 OUTPUT_PATH=./result
 
-$BINARY_PATH --widths $WIDTHS --sizes $SIZES --output $OUTPUT_PATH
+$BINARY_PATH --widths $WIDTHS --sizes $SIZES --hashers $HASHERS --output $OUTPUT_PATH
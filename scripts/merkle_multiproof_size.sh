@@ -0,0 +1,14 @@
+#!/bin/bash
+
+if [ "$1" == "debug" ]; then
+    BINARY_PATH=./target/debug/merkle_multiproof_size
+else
+    BINARY_PATH=./target/release/merkle_multiproof_size
+fi
+
+WIDTHS=2,4,8,16
+SIZES=1000,100000
+OPENINGS=1,10,100,1000
+OUTPUT_PATH=./result
+
+$BINARY_PATH --widths $WIDTHS --sizes $SIZES --openings $OPENINGS --output $OUTPUT_PATH
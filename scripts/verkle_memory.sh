@@ -0,0 +1,13 @@
+#!/bin/bash
+
+if [ "$1" == "debug" ]; then
+    BINARY_PATH=./target/debug/verkle_memory
+else
+    BINARY_PATH=./target/release/verkle_memory
+fi
+
+WIDTHS=2,4,8,32,64,256,512,1024
+SIZES=1000000
+OUTPUT_PATH=./result
+
+$BINARY_PATH --widths $WIDTHS --sizes $SIZES --output $OUTPUT_PATH
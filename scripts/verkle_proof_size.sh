@@ -0,0 +1,14 @@
+#!/bin/bash
+
+if [ "$1" == "debug" ]; then
+    BINARY_PATH=./target/debug/verkle_proof_size
+else
+    BINARY_PATH=./target/release/verkle_proof_size
+fi
+
+WIDTHS=2,4,8,32,64,256
+HEIGHT=8
+SIZES=100,1000
+OUTPUT_PATH=./result
+
+$BINARY_PATH --widths $WIDTHS --height $HEIGHT --sizes $SIZES --output $OUTPUT_PATH
@@ -0,0 +1,492 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeInclusive,
+};
+
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInteger, PrimeField};
+use ark_poly::EvaluationDomain;
+use ark_poly_commit::kzg10::{Powers, VerifierKey};
+use ark_std::{rand::RngCore, Zero};
+use keccak_asm::{Digest, Keccak256};
+
+use crate::{
+    data_structures::Proof,
+    error::VerkleError,
+    tree::{Commitment, Domain, PCSMultiproof, Poly, VerkleTree, PCS},
+};
+
+/// Node of a `CompressedVerkleTree`. A subtree that holds a single value is collapsed into a
+/// `Stem` that keeps the full index of the value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompressedNode {
+    Internal { value: Commitment, evals: Vec<Fr> },
+    Stem { index: usize, value: Fr },
+}
+
+/// Index and value held by a `CompressedNode::Stem`.
+pub type Stem = (usize, Fr);
+
+/// Verkle tree over a fixed index space of `width^height` slots like `SparseVerkleTree`, where
+/// chains of single-child nodes are collapsed into stems as in the Ethereum verkle design. Empty
+/// children evaluate to zero and a stem evaluates to `hash_stem(index, value)`, so the path to a
+/// value stops at the depth where its index diverges from every other populated index and a
+/// proof only carries the commitments above it. `VerkleTree` keeps fixed-length paths, which the
+/// EVM verifier and wire format rely on.
+#[derive(Debug)]
+pub struct CompressedVerkleTree<'a> {
+    pub nodes: HashMap<(usize, usize), CompressedNode>,
+    pub leaves: BTreeMap<usize, Fr>,
+    pub height: usize,
+    pub width: usize,
+    pub domain_size: usize,
+    pub ck: Powers<'a, Bn254>,
+    pub vk: VerifierKey<Bn254>,
+}
+
+impl CompressedVerkleTree<'_> {
+    pub fn setup<R: RngCore>(
+        width: usize,
+        height: usize,
+        rng: &mut R,
+    ) -> Result<Self, VerkleError> {
+        if height == 0 {
+            return Err(VerkleError::InvalidHeight(height));
+        }
+        let domain_size = VerkleTree::domain_size(width)?;
+        let pp = PCS::setup(domain_size, false, rng)?;
+        let (ck, vk) = PCS::trim(pp, domain_size)?;
+
+        let mut tree = CompressedVerkleTree {
+            nodes: HashMap::new(),
+            leaves: BTreeMap::new(),
+            height,
+            width,
+            domain_size,
+            ck,
+            vk,
+        };
+        tree.rebuild(0, 0, 0)?;
+        Ok(tree)
+    }
+
+    pub fn root_hash(&self) -> Fr {
+        match self.nodes.get(&(0, 0)) {
+            Some(CompressedNode::Internal { value, .. }) => VerkleTree::hash_g1(&value.0),
+            _ => unreachable!("Root is always an internal node"),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Fr {
+        self.leaves.get(&index).copied().unwrap_or_else(Fr::zero)
+    }
+
+    pub fn insert(&mut self, index: usize, value: Fr) -> Result<(), VerkleError> {
+        if !self.is_in_range(index) {
+            return Err(VerkleError::IndexOutOfRange(index));
+        }
+
+        if value.is_zero() {
+            self.leaves.remove(&index);
+        } else {
+            self.leaves.insert(index, value);
+        }
+        self.rebuild(0, 0, index)?;
+        Ok(())
+    }
+
+    pub fn commit(&mut self, values: &[(usize, Fr)]) -> Result<(), VerkleError> {
+        for &(index, value) in values {
+            self.insert(index, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn open(&self, index: usize) -> Result<(Fr, Proof<Bn254>), VerkleError> {
+        match self.prove_path(index)? {
+            (Some((stem, value)), proof) if stem == index => Ok((value, proof)),
+            _ => Err(VerkleError::IndexEmpty(index)),
+        }
+    }
+
+    pub fn verify(
+        &self,
+        index: usize,
+        value: Fr,
+        multi_proof: Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        self.verify_path(index, hash_stem(index, &value), &multi_proof)
+    }
+
+    /// Proves that `index` holds no value. The path either ends at an empty child, or at the stem
+    /// of another index with the same path prefix, which is returned with the proof.
+    pub fn open_absence(&self, index: usize) -> Result<(Option<Stem>, Proof<Bn254>), VerkleError> {
+        match self.prove_path(index)? {
+            (Some((stem, _)), _) if stem == index => Err(VerkleError::IndexNotEmpty(index)),
+            result => Ok(result),
+        }
+    }
+
+    pub fn verify_absence(
+        &self,
+        index: usize,
+        stem: Option<Stem>,
+        multi_proof: Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        let eval = match stem {
+            None => Fr::zero(),
+            Some((other, value)) => {
+                // The stem has to sit on the path of `index` below the last commitment
+                let depth = multi_proof.coms.len();
+                let path = VerkleTree::compute_path(index, self.height, self.width);
+                let other_path = VerkleTree::compute_path(other, self.height, self.width);
+                if other == index
+                    || !self.is_in_range(other)
+                    || path.get(..depth) != other_path.get(..depth)
+                {
+                    return Ok(false);
+                }
+                hash_stem(other, &value)
+            }
+        };
+        self.verify_path(index, eval, &multi_proof)
+    }
+
+    /// Opens the internal nodes on the path of `index` and returns the stem the path ends at, if
+    /// any.
+    fn prove_path(&self, index: usize) -> Result<(Option<Stem>, Proof<Bn254>), VerkleError> {
+        if !self.is_in_range(index) {
+            return Err(VerkleError::IndexOutOfRange(index));
+        }
+        let tree_path = VerkleTree::compute_path(index, self.height, self.width);
+        let domain = Domain::new(self.width).unwrap();
+
+        let mut polys = Vec::<Poly>::new();
+        let mut coms = Vec::<Commitment>::new();
+        let mut points = Vec::<Fr>::new();
+        let mut values = Vec::<Fr>::new();
+        let mut stem = None;
+        let mut position = 0;
+        for (depth, &path_index) in tree_path.iter().enumerate() {
+            let Some(CompressedNode::Internal { value, evals }) =
+                self.nodes.get(&(depth, position))
+            else {
+                unreachable!("Paths only continue below internal nodes");
+            };
+            polys.push(VerkleTree::gen_poly_from_evals(evals, self.width));
+            coms.push(*value);
+            points.push(domain.element(path_index));
+            values.push(evals[path_index]);
+
+            position = position * self.width + path_index;
+            match self.nodes.get(&(depth + 1, position)) {
+                Some(CompressedNode::Internal { .. }) => continue,
+                Some(CompressedNode::Stem { index, value }) => stem = Some((*index, *value)),
+                None => {}
+            }
+            break;
+        }
+
+        let multi_proof = PCSMultiproof::prove(&self.ck, &polys, &coms, &points, &values)?;
+        Ok((stem, Proof { coms, multi_proof }))
+    }
+
+    /// Verifies a path of internal nodes from the root of this tree that ends with `eval` at the
+    /// child on the path of `index` of the last commitment.
+    fn verify_path(
+        &self,
+        index: usize,
+        eval: Fr,
+        multi_proof: &Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        if !self.is_in_range(index) {
+            return Err(VerkleError::IndexOutOfRange(index));
+        }
+        let length = multi_proof.coms.len();
+        if length == 0 || length > self.height {
+            return Err(VerkleError::MalformedProof {
+                length,
+                height: self.height,
+            });
+        }
+        if VerkleTree::hash_g1(&multi_proof.coms[0].0) != self.root_hash() {
+            return Ok(false);
+        }
+
+        let tree_path = VerkleTree::compute_path(index, self.height, self.width);
+        let domain = Domain::new(self.width).unwrap();
+        let points: Vec<Fr> = tree_path[..length]
+            .iter()
+            .map(|path_index| domain.element(*path_index))
+            .collect();
+        let mut values: Vec<Fr> = multi_proof.coms[1..]
+            .iter()
+            .map(|com| VerkleTree::hash_g1(&com.0))
+            .collect();
+        values.push(eval);
+        let result = PCSMultiproof::verify(
+            &self.vk,
+            &multi_proof.coms,
+            &points,
+            &values,
+            &multi_proof.multi_proof,
+        )?;
+        Ok(result)
+    }
+
+    /// Rebuilds the node at `(depth, position)` after the value at `index` below it has changed
+    /// and returns its evaluation in the parent. Only the child on the path of `index` is
+    /// rebuilt, except for a stem that has to be pushed down because it now shares its node.
+    fn rebuild(&mut self, depth: usize, position: usize, index: usize) -> Result<Fr, VerkleError> {
+        let mut values = self.leaves.range(self.subtree(depth, position));
+        let first = values.next().map(|(index, value)| (*index, *value));
+        if depth > 0 && values.next().is_none() {
+            self.remove_subtree(depth, position);
+            return Ok(match first {
+                Some((index, value)) => {
+                    let node = CompressedNode::Stem { index, value };
+                    self.nodes.insert((depth, position), node);
+                    hash_stem(index, &value)
+                }
+                None => Fr::zero(),
+            });
+        }
+
+        let path_position = self.position(index, depth + 1);
+        let first_child = position * self.width;
+        let mut evals = Vec::with_capacity(self.width);
+        for child in first_child..first_child + self.width {
+            let eval = if child == path_position {
+                self.rebuild(depth + 1, child, index)?
+            } else if let Some(node) = self.nodes.get(&(depth + 1, child)) {
+                Self::node_eval(node)
+            } else if self
+                .leaves
+                .range(self.subtree(depth + 1, child))
+                .next()
+                .is_some()
+            {
+                self.rebuild(depth + 1, child, index)?
+            } else {
+                Fr::zero()
+            };
+            evals.push(eval);
+        }
+
+        let poly = VerkleTree::gen_poly_from_evals(&evals, self.width);
+        let (com, _) = PCS::commit(&self.ck, &poly, None, None)?;
+        let node = CompressedNode::Internal { value: com, evals };
+        self.nodes.insert((depth, position), node);
+        Ok(VerkleTree::hash_g1(&com.0))
+    }
+
+    fn remove_subtree(&mut self, depth: usize, position: usize) {
+        if let Some(CompressedNode::Internal { .. }) = self.nodes.remove(&(depth, position)) {
+            let first_child = position * self.width;
+            for child in first_child..first_child + self.width {
+                self.remove_subtree(depth + 1, child);
+            }
+        }
+    }
+
+    fn node_eval(node: &CompressedNode) -> Fr {
+        match node {
+            CompressedNode::Internal { value, .. } => VerkleTree::hash_g1(&value.0),
+            CompressedNode::Stem { index, value } => hash_stem(*index, value),
+        }
+    }
+
+    /// Returns the position of the node above `index` at `depth`.
+    fn position(&self, index: usize, depth: usize) -> usize {
+        let tree_path = VerkleTree::compute_path(index, self.height, self.width);
+        tree_path[..depth]
+            .iter()
+            .fold(0, |position, path_index| position * self.width + path_index)
+    }
+
+    /// Returns the indices below the node at `(depth, position)`.
+    fn subtree(&self, depth: usize, position: usize) -> RangeInclusive<usize> {
+        match self.width.checked_pow((self.height - depth) as u32) {
+            Some(span) => {
+                let first = position * span;
+                first..=first.saturating_add(span - 1)
+            }
+            None => 0..=usize::MAX,
+        }
+    }
+
+    fn is_in_range(&self, index: usize) -> bool {
+        VerkleTree::is_in_range(index, self.height, self.width)
+    }
+}
+
+/// Hashes a stem into the evaluation of its parent. The 40-byte preimage, an 8-byte index followed
+/// by the 32-byte value, cannot be confused with the 32-byte words hashed by `hash_g1`.
+pub(crate) fn hash_stem(index: usize, value: &Fr) -> Fr {
+    let mut hasher = Keccak256::new();
+    hasher.update((index as u64).to_be_bytes());
+    hasher.update(value.into_bigint().to_bytes_be());
+    let result = hasher.finalize();
+    Fr::from_be_bytes_mod_order(result.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sparse::SparseVerkleTree;
+    use ark_std::{rand::Rng, test_rng};
+
+    #[test]
+    fn it_collapses_single_value_subtrees() {
+        let mut rng = test_rng();
+        let mut tree = CompressedVerkleTree::setup(16, 4, &mut rng).unwrap();
+
+        tree.insert(3, Fr::from(30)).unwrap();
+        assert_eq!(tree.nodes.len(), 2);
+        let (value, proof) = tree.open(3).unwrap();
+        assert_eq!(value, Fr::from(30));
+        assert_eq!(proof.coms.len(), 1);
+        assert!(tree.verify(3, value, proof).unwrap());
+    }
+
+    #[test]
+    fn it_splits_and_merges_stems() {
+        let mut rng = test_rng();
+        let mut tree = CompressedVerkleTree::setup(16, 4, &mut rng).unwrap();
+        tree.insert(3, Fr::from(30)).unwrap();
+        let root_hash = tree.root_hash();
+
+        // 3 and 5 only diverge at the last level
+        tree.insert(5, Fr::from(50)).unwrap();
+        assert_eq!(tree.nodes.len(), 4 + 2);
+        for (index, value) in [(3, Fr::from(30)), (5, Fr::from(50))] {
+            let (opened, proof) = tree.open(index).unwrap();
+            assert_eq!(opened, value);
+            assert_eq!(proof.coms.len(), 4);
+            assert!(tree.verify(index, value, proof).unwrap());
+        }
+
+        tree.insert(5, Fr::zero()).unwrap();
+        assert_eq!(tree.nodes.len(), 2);
+        assert_eq!(tree.root_hash(), root_hash);
+    }
+
+    #[test]
+    fn it_does_not_depend_on_insertion_order() {
+        let entries = vec![
+            (3, Fr::from(30)),
+            (5, Fr::from(50)),
+            (4000, Fr::from(40)),
+            (4001, Fr::from(41)),
+        ];
+        let mut rng = test_rng();
+        let mut tree = CompressedVerkleTree::setup(16, 4, &mut rng).unwrap();
+        tree.commit(entries.as_slice()).unwrap();
+        let mut rng = test_rng();
+        let mut reversed_tree = CompressedVerkleTree::setup(16, 4, &mut rng).unwrap();
+        let reversed: Vec<(usize, Fr)> = entries.iter().rev().copied().collect();
+        reversed_tree.commit(reversed.as_slice()).unwrap();
+
+        assert_eq!(tree.root_hash(), reversed_tree.root_hash());
+        assert_eq!(tree.nodes, reversed_tree.nodes);
+    }
+
+    #[test]
+    fn it_verifies_absence() {
+        let mut rng = test_rng();
+        let mut tree = CompressedVerkleTree::setup(16, 4, &mut rng).unwrap();
+        tree.commit(&[(3, Fr::from(30)), (4000, Fr::from(40))])
+            .unwrap();
+
+        // Path that ends at an empty child
+        let (stem, proof) = tree.open_absence(8000).unwrap();
+        assert_eq!(stem, None);
+        assert!(tree.verify_absence(8000, stem, proof).unwrap());
+
+        // Path that ends at the stem of another index
+        let (stem, proof) = tree.open_absence(7).unwrap();
+        assert_eq!(stem, Some((3, Fr::from(30))));
+        assert!(tree.verify_absence(7, stem, proof.clone()).unwrap());
+        assert!(!tree
+            .verify_absence(7, Some((3, Fr::from(31))), proof.clone())
+            .unwrap());
+        assert!(!tree.verify_absence(7, None, proof.clone()).unwrap());
+        assert!(!tree.verify_absence(3, stem, proof).unwrap());
+
+        let (_, proof) = tree.open_absence(4095).unwrap();
+        assert!(!tree
+            .verify_absence(4095, Some((3, Fr::from(30))), proof)
+            .unwrap());
+    }
+
+    #[test]
+    fn it_rejects_proofs_from_another_root() {
+        let mut rng = test_rng();
+        let mut tree = CompressedVerkleTree::setup(16, 4, &mut rng).unwrap();
+        tree.commit(&[(3, Fr::from(30)), (4000, Fr::from(40))])
+            .unwrap();
+
+        // Same setup parameters, so the forged chain is consistent on its own
+        let mut rng = test_rng();
+        let mut forged_tree = CompressedVerkleTree::setup(16, 4, &mut rng).unwrap();
+        forged_tree
+            .commit(&[(3, Fr::from(31)), (7, Fr::from(70))])
+            .unwrap();
+
+        let (value, proof) = forged_tree.open(3).unwrap();
+        assert!(forged_tree.verify(3, value, proof.clone()).unwrap());
+        assert!(!tree.verify(3, value, proof).unwrap());
+
+        let (stem, proof) = forged_tree.open_absence(4000).unwrap();
+        assert!(forged_tree
+            .verify_absence(4000, stem, proof.clone())
+            .unwrap());
+        assert!(!tree.verify_absence(4000, stem, proof).unwrap());
+    }
+
+    #[test]
+    fn it_does_not_open_wrong_kind_of_proof() {
+        let mut rng = test_rng();
+        let mut tree = CompressedVerkleTree::setup(16, 4, &mut rng).unwrap();
+        tree.insert(3, Fr::from(30)).unwrap();
+
+        assert!(matches!(tree.open(7), Err(VerkleError::IndexEmpty(7))));
+        let result = tree.open_absence(3);
+        assert!(matches!(result, Err(VerkleError::IndexNotEmpty(3))));
+        let result = tree.open(65536);
+        assert!(matches!(result, Err(VerkleError::IndexOutOfRange(65536))));
+        let result = tree.insert(65536, Fr::from(1));
+        assert!(matches!(result, Err(VerkleError::IndexOutOfRange(65536))));
+    }
+
+    #[test]
+    fn it_rejects_invalid_setup() {
+        let mut rng = test_rng();
+        let result = CompressedVerkleTree::setup(16, 0, &mut rng);
+        assert!(matches!(result, Err(VerkleError::InvalidHeight(0))));
+        let result = CompressedVerkleTree::setup(1, 4, &mut rng);
+        assert!(matches!(result, Err(VerkleError::InvalidWidth(1))));
+    }
+
+    #[test]
+    fn it_shortens_proofs_on_sparse_workload() {
+        let width = 16;
+        let height = 6;
+        let mut rng = test_rng();
+        let mut tree = CompressedVerkleTree::setup(width, height, &mut rng).unwrap();
+        let mut sparse_tree = SparseVerkleTree::setup(width, height, &mut rng).unwrap();
+
+        let entries: Vec<(usize, Fr)> = (1..=32)
+            .map(|value| (rng.gen_range(0..1 << 24), Fr::from(value)))
+            .collect();
+        tree.commit(entries.as_slice()).unwrap();
+        sparse_tree.commit(entries.as_slice()).unwrap();
+
+        for &(index, value) in entries.iter() {
+            let (_, proof) = tree.open(index).unwrap();
+            let (_, sparse_proof) = sparse_tree.open(index).unwrap();
+            assert!(proof.coms.len() < sparse_proof.coms.len());
+            assert!(tree.verify(index, value, proof).unwrap());
+        }
+    }
+}
@@ -1,9 +1,302 @@
-use ark_ec::pairing::Pairing;
+use ark_bn254::{Bn254, Fq, Fr, G1Affine};
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_ff::{BigInteger, PrimeField};
 use ark_poly_commit::kzg10::Commitment;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use derivative::Derivative;
 use kzg_commitment::data_structures::Proof as KZGCommitmentProof;
 
-#[derive(Debug)]
+/// `Proof` is a verkle path proof that is output by `VerkleTree::open`.
+#[derive(Derivative, CanonicalSerialize, CanonicalDeserialize)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
 pub struct Proof<E: Pairing> {
     pub coms: Vec<Commitment<E>>,
-    pub multi_proof: KZGCommitmentProof<E>
+    pub multi_proof: KZGCommitmentProof<E>,
+}
+
+/// `TransitionWitness` proves that applying a set of updates to the tree with root A gives root
+/// B. It is output by `VerkleTree::prove_transition`, with `pre_values` in the order of the
+/// updates, and `coms` and `new_coms` holding the commitments of every node on the updated paths
+/// before and after the updates, level by level from the root and from left to right within a
+/// level. `multi_proof` opens `coms` at the updated children against root A.
+#[derive(Derivative, CanonicalSerialize, CanonicalDeserialize)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
+pub struct TransitionWitness<E: Pairing> {
+    pub pre_values: Vec<E::ScalarField>,
+    pub coms: Vec<Commitment<E>>,
+    pub new_coms: Vec<Commitment<E>>,
+    pub multi_proof: KZGCommitmentProof<E>,
+}
+
+/// Whether a `Proof` carries the root commitment. With `OmitRoot` the verifier supplies the root
+/// commitment it already stores and `coms` only holds the commitments below the root.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProofMode {
+    #[default]
+    WithRoot,
+    OmitRoot,
+}
+
+/// `Opening` is a `Proof` together with the index and value it opens.
+///
+/// The compressed encoding (`serialize_compressed`) follows the arkworks canonical format, with
+/// little-endian integers and 32-byte compressed G1 points for BN254:
+///
+/// | field          | size                       |
+/// |----------------|----------------------------|
+/// | index          | 8 bytes, `u64`             |
+/// | value          | 32 bytes                   |
+/// | coms           | 8 bytes `u64` length `n`, then `n` points of 32 bytes |
+/// | multi_proof.d  | 32 bytes                   |
+/// | multi_proof.pi | 32 bytes                   |
+///
+/// `serialize_uncompressed` has the same layout with 64-byte points. The EVM encoding
+/// (`to_evm_bytes`) is a sequence of 32-byte big-endian words, with points as `(x, y)` pairs and
+/// the point at infinity as `(0, 0)`, in the order they are passed to `VerkleVerifier.verify`:
+/// `index, value, n, coms[0].x, coms[0].y, ..., coms[n - 1].y, d.x, d.y, pi.x, pi.y`.
+#[derive(Derivative, CanonicalSerialize, CanonicalDeserialize)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
+pub struct Opening<E: Pairing> {
+    pub index: u64,
+    pub value: E::ScalarField,
+    pub proof: Proof<E>,
+}
+
+const WORD_SIZE: usize = 32;
+
+impl Opening<Bn254> {
+    pub fn to_evm_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(Self::u64_to_word(self.index));
+        bytes.extend(Self::field_to_word(&self.value));
+        bytes.extend(Self::u64_to_word(self.proof.coms.len() as u64));
+        for com in self.proof.coms.iter() {
+            bytes.extend(Self::point_to_words(&com.0));
+        }
+        bytes.extend(Self::point_to_words(&self.proof.multi_proof.d.0));
+        bytes.extend(Self::point_to_words(&self.proof.multi_proof.pi));
+        bytes
+    }
+
+    pub fn from_evm_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        if !bytes.len().is_multiple_of(WORD_SIZE) || bytes.len() < 7 * WORD_SIZE {
+            return Err(SerializationError::InvalidData);
+        }
+        let mut words = bytes.chunks(WORD_SIZE);
+        let mut next_word = || words.next().ok_or(SerializationError::InvalidData);
+
+        let index = Self::word_to_u64(next_word()?)?;
+        let value = Self::word_to_field::<Fr>(next_word()?)?;
+        let coms_len = Self::word_to_u64(next_word()?)?;
+        let coms_words = bytes.len() / WORD_SIZE - 7;
+        if !coms_words.is_multiple_of(2) || coms_len != (coms_words / 2) as u64 {
+            return Err(SerializationError::InvalidData);
+        }
+        let coms_len = coms_words / 2;
+
+        let mut coms = Vec::with_capacity(coms_len);
+        for _ in 0..coms_len {
+            coms.push(Commitment(Self::words_to_point(
+                next_word()?,
+                next_word()?,
+            )?));
+        }
+        let d = Commitment(Self::words_to_point(next_word()?, next_word()?)?);
+        let pi = Self::words_to_point(next_word()?, next_word()?)?;
+
+        Ok(Opening {
+            index,
+            value,
+            proof: Proof {
+                coms,
+                multi_proof: KZGCommitmentProof { d, pi },
+            },
+        })
+    }
+
+    fn u64_to_word(n: u64) -> [u8; WORD_SIZE] {
+        let mut word = [0u8; WORD_SIZE];
+        word[WORD_SIZE - 8..].copy_from_slice(&n.to_be_bytes());
+        word
+    }
+
+    fn field_to_word<F: PrimeField>(element: &F) -> [u8; WORD_SIZE] {
+        let bytes = element.into_bigint().to_bytes_be();
+        let mut word = [0u8; WORD_SIZE];
+        word[WORD_SIZE - bytes.len()..].copy_from_slice(&bytes);
+        word
+    }
+
+    fn point_to_words(point: &G1Affine) -> Vec<u8> {
+        let (x, y) = point.xy().unwrap_or_default();
+        [Self::field_to_word(&x), Self::field_to_word(&y)].concat()
+    }
+
+    fn word_to_u64(word: &[u8]) -> Result<u64, SerializationError> {
+        if word[..WORD_SIZE - 8].iter().any(|byte| *byte != 0) {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(u64::from_be_bytes(
+            word[WORD_SIZE - 8..].try_into().unwrap(),
+        ))
+    }
+
+    fn word_to_field<F: PrimeField>(word: &[u8]) -> Result<F, SerializationError> {
+        // Reject non-canonical encodings instead of reducing them modulo the field order
+        let element = F::from_be_bytes_mod_order(word);
+        if element.into_bigint().to_bytes_be() != word {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(element)
+    }
+
+    fn words_to_point(x: &[u8], y: &[u8]) -> Result<G1Affine, SerializationError> {
+        let x = Self::word_to_field::<Fq>(x)?;
+        let y = Self::word_to_field::<Fq>(y)?;
+        if x == Fq::from(0) && y == Fq::from(0) {
+            return Ok(G1Affine::identity());
+        }
+
+        let point = G1Affine::new_unchecked(x, y);
+        if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::VerkleTree;
+    use ark_std::test_rng;
+
+    fn open_tree(index: usize) -> Opening<Bn254> {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let (value, proof) = tree.open(index).unwrap();
+        Opening {
+            index: index as u64,
+            value,
+            proof,
+        }
+    }
+
+    #[test]
+    fn it_round_trips_canonical_encoding() {
+        let opening = open_tree(2);
+
+        let mut bytes = Vec::new();
+        opening.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + 32 + 8 + 32 * 3 + 32 * 2);
+        let decoded = Opening::<Bn254>::deserialize_compressed(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, opening);
+
+        let mut bytes = Vec::new();
+        opening.serialize_uncompressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + 32 + 8 + 64 * 3 + 64 * 2);
+        let decoded = Opening::<Bn254>::deserialize_uncompressed(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, opening);
+    }
+
+    #[test]
+    fn it_round_trips_evm_encoding() {
+        let opening = open_tree(2);
+
+        let bytes = opening.to_evm_bytes();
+        assert_eq!(bytes.len(), 32 * (3 + 2 * 3 + 4));
+        assert_eq!(Opening::from_evm_bytes(&bytes).unwrap(), opening);
+    }
+
+    #[test]
+    fn it_verifies_decoded_proof() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let index = 5;
+        let (value, proof) = tree.open(index).unwrap();
+        let opening = Opening {
+            index: index as u64,
+            value,
+            proof,
+        };
+        let decoded = Opening::<Bn254>::from_evm_bytes(&opening.to_evm_bytes()).unwrap();
+        let result = tree.verify(decoded.index as usize, decoded.value, decoded.proof);
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn it_rejects_truncated_canonical_encoding() {
+        let opening = open_tree(2);
+
+        let mut bytes = Vec::new();
+        opening.serialize_compressed(&mut bytes).unwrap();
+        bytes.pop();
+        assert!(Opening::<Bn254>::deserialize_compressed(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn it_rejects_point_not_on_curve() {
+        let opening = open_tree(2);
+
+        let mut bytes = Vec::new();
+        opening.serialize_uncompressed(&mut bytes).unwrap();
+        // Flip a bit of the y coordinate of the first commitment
+        bytes[8 + 32 + 8 + 32] ^= 1;
+        assert!(Opening::<Bn254>::deserialize_uncompressed(bytes.as_slice()).is_err());
+
+        let mut bytes = opening.to_evm_bytes();
+        bytes[32 * 5 - 1] ^= 1;
+        assert!(Opening::from_evm_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn it_rejects_malformed_evm_encoding() {
+        let opening = open_tree(2);
+        let bytes = opening.to_evm_bytes();
+
+        // Length that is not a whole number of words
+        assert!(Opening::from_evm_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        // Number of commitments that does not match the length
+        let mut wrong_len = bytes.clone();
+        wrong_len[32 * 3 - 1] += 1;
+        assert!(Opening::from_evm_bytes(&wrong_len).is_err());
+
+        // Value that is not reduced modulo the field order
+        let mut wrong_value = bytes.clone();
+        wrong_value[32..64].copy_from_slice(&[0xff; 32]);
+        assert!(Opening::from_evm_bytes(&wrong_value).is_err());
+
+        // Index that does not fit in 64 bits
+        let mut wrong_index = bytes.clone();
+        wrong_index[0] = 1;
+        assert!(Opening::from_evm_bytes(&wrong_index).is_err());
+    }
 }
@@ -0,0 +1,88 @@
+use std::{fmt, io};
+
+use ark_poly_commit::Error as PCSError;
+
+#[derive(Debug)]
+pub enum VerkleError {
+    /// The width is below 2 or has no evaluation domain.
+    InvalidWidth(usize),
+    /// The height of a fixed-size tree is 0.
+    InvalidHeight(usize),
+    /// The tree has not been committed to yet.
+    NotCommitted,
+    /// `commit` was called with an empty vector.
+    EmptyVector,
+    /// The index is not a leaf of the committed tree.
+    IndexOutOfRange(usize),
+    /// The range `[start, end)` is empty.
+    InvalidRange {
+        start: usize,
+        end: usize,
+    },
+    /// The index appears more than once in a set of updates.
+    DuplicateIndex(usize),
+    /// The index holds no value.
+    IndexEmpty(usize),
+    /// The leaf at the index holds a field element instead of a byte value.
+    NotBytes(usize),
+    /// An absence proof was requested for an index that holds a value.
+    IndexNotEmpty(usize),
+    /// The version was never committed or has been pruned.
+    UnknownVersion(u64),
+    /// The proof does not carry the number of commitments expected for a tree of this height.
+    MalformedProof {
+        length: usize,
+        height: usize,
+    },
+    Kzg(PCSError),
+    Store(io::Error),
+}
+
+impl fmt::Display for VerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerkleError::InvalidWidth(width) => write!(f, "Width {} is not supported", width),
+            VerkleError::InvalidHeight(height) => write!(f, "Height {} is not supported", height),
+            VerkleError::NotCommitted => write!(f, "Tree is not committed"),
+            VerkleError::EmptyVector => write!(f, "Cannot commit to an empty vector"),
+            VerkleError::IndexOutOfRange(index) => write!(f, "Index {} is out of range", index),
+            VerkleError::InvalidRange { start, end } => {
+                write!(f, "Range [{}, {}) is empty", start, end)
+            }
+            VerkleError::DuplicateIndex(index) => write!(f, "Index {} is updated twice", index),
+            VerkleError::IndexEmpty(index) => write!(f, "Index {} is empty", index),
+            VerkleError::NotBytes(index) => write!(f, "Leaf {} does not hold bytes", index),
+            VerkleError::IndexNotEmpty(index) => write!(f, "Index {} is not empty", index),
+            VerkleError::UnknownVersion(version) => write!(f, "Unknown version {}", version),
+            VerkleError::MalformedProof { length, height } => write!(
+                f,
+                "Proof has {} commitments for a tree of height {}",
+                length, height
+            ),
+            VerkleError::Kzg(e) => write!(f, "KZG error: {}", e),
+            VerkleError::Store(e) => write!(f, "Store error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerkleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VerkleError::Kzg(e) => Some(e),
+            VerkleError::Store(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PCSError> for VerkleError {
+    fn from(e: PCSError) -> Self {
+        VerkleError::Kzg(e)
+    }
+}
+
+impl From<io::Error> for VerkleError {
+    fn from(e: io::Error) -> Self {
+        VerkleError::Store(e)
+    }
+}
@@ -1,2 +1,7 @@
 pub mod tree;
 pub mod data_structures;
+pub mod sparse;
+pub mod store;
+pub mod error;
+pub mod transition;
+pub mod compressed;
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use ark_bn254::{Bn254, Fr};
+use ark_poly::{EvaluationDomain, Polynomial};
+use ark_poly_commit::kzg10::{Powers, VerifierKey};
+use ark_std::{rand::RngCore, Zero};
+
+use crate::{
+    data_structures::Proof,
+    error::VerkleError,
+    tree::{Commitment, Domain, PCSMultiproof, Poly, VerkleTree, PCS},
+};
+
+/// Verkle tree over a fixed index space of `width^height` slots where only populated paths are
+/// stored. Unset slots hold `Fr::zero()` and every empty subtree is represented by a precomputed
+/// default commitment of its level, so proofs have the same shape as `VerkleTree` proofs.
+#[derive(Debug)]
+pub struct SparseVerkleTree<'a> {
+    pub nodes: HashMap<(usize, usize), Commitment>,
+    pub leaves: HashMap<usize, Fr>,
+    pub height: usize,
+    pub width: usize,
+    pub domain_size: usize,
+    pub ck: Powers<'a, Bn254>,
+    pub vk: VerifierKey<Bn254>,
+    default_coms: Vec<Commitment>,
+    default_hashes: Vec<Fr>,
+}
+
+impl SparseVerkleTree<'_> {
+    pub fn setup<R: RngCore>(
+        width: usize,
+        height: usize,
+        rng: &mut R,
+    ) -> Result<Self, VerkleError> {
+        let domain_size = VerkleTree::domain_size(width)?;
+        let pp = PCS::setup(domain_size, false, rng)?;
+        let (ck, vk) = PCS::trim(pp, domain_size)?;
+        let (default_coms, default_hashes) = Self::compute_defaults(&ck, width, height)?;
+
+        Ok(SparseVerkleTree {
+            nodes: HashMap::new(),
+            leaves: HashMap::new(),
+            height,
+            width,
+            domain_size,
+            ck,
+            vk,
+            default_coms,
+            default_hashes,
+        })
+    }
+
+    pub fn root_hash(&self) -> Fr {
+        VerkleTree::hash_g1(&self.node_commitment(0, 0).0)
+    }
+
+    pub fn get(&self, index: usize) -> Fr {
+        self.leaves.get(&index).copied().unwrap_or_else(Fr::zero)
+    }
+
+    pub fn insert(&mut self, index: usize, value: Fr) -> Result<(), VerkleError> {
+        if !self.is_in_range(index) {
+            return Err(VerkleError::IndexOutOfRange(index));
+        }
+
+        if value.is_zero() {
+            self.leaves.remove(&index);
+        } else {
+            self.leaves.insert(index, value);
+        }
+
+        // Recommit every node on the path from the parent of the leaf up to the root
+        let mut position = index;
+        for depth in (0..self.height).rev() {
+            position /= self.width;
+            let evals = self.child_evals(depth, position);
+            let poly = VerkleTree::gen_poly_from_evals(&evals, self.width);
+            let (com, _) = PCS::commit(&self.ck, &poly, None, None)?;
+            if com == self.default_coms[depth] {
+                self.nodes.remove(&(depth, position));
+            } else {
+                self.nodes.insert((depth, position), com);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn commit(&mut self, values: &[(usize, Fr)]) -> Result<(), VerkleError> {
+        for &(index, value) in values {
+            self.insert(index, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn open(&self, index: usize) -> Result<(Fr, Proof<Bn254>), VerkleError> {
+        if !self.is_in_range(index) {
+            return Err(VerkleError::IndexOutOfRange(index));
+        }
+        let tree_path = VerkleTree::compute_path(index, self.height, self.width);
+        let domain = Domain::new(self.width).unwrap();
+
+        let mut polys = Vec::<Poly>::new();
+        let mut coms = Vec::<Commitment>::new();
+        let mut points = Vec::<Fr>::new();
+        let mut values = Vec::<Fr>::new();
+        let mut position = 0;
+        for (depth, &path_index) in tree_path.iter().enumerate() {
+            let evals = self.child_evals(depth, position);
+            let poly = VerkleTree::gen_poly_from_evals(&evals, self.width);
+            let point = domain.element(path_index);
+            values.push(poly.evaluate(&point));
+            polys.push(poly);
+            coms.push(self.node_commitment(depth, position));
+            points.push(point);
+            position = position * self.width + path_index;
+        }
+
+        let multi_proof = PCSMultiproof::prove(&self.ck, &polys, &coms, &points, &values)?;
+        Ok((self.get(index), Proof { coms, multi_proof }))
+    }
+
+    /// Verifies a proof against the root of this tree.
+    pub fn verify(
+        &self,
+        index: usize,
+        value: Fr,
+        multi_proof: Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        let result = VerkleTree::verify_path(
+            &self.vk,
+            self.width,
+            self.height,
+            index,
+            value,
+            &multi_proof,
+        )?;
+        Ok(result && VerkleTree::hash_g1(&multi_proof.coms[0].0) == self.root_hash())
+    }
+
+    fn is_in_range(&self, index: usize) -> bool {
+        VerkleTree::is_in_range(index, self.height, self.width)
+    }
+
+    fn node_commitment(&self, depth: usize, position: usize) -> Commitment {
+        match self.nodes.get(&(depth, position)) {
+            Some(com) => *com,
+            None => self.default_coms[depth],
+        }
+    }
+
+    fn child_evals(&self, depth: usize, position: usize) -> Vec<Fr> {
+        let first_child = position * self.width;
+        (0..self.width)
+            .map(|i| {
+                let child = first_child + i;
+                if depth + 1 == self.height {
+                    VerkleTree::hash_fr(&self.get(child))
+                } else {
+                    match self.nodes.get(&(depth + 1, child)) {
+                        Some(com) => VerkleTree::hash_g1(&com.0),
+                        None => self.default_hashes[depth + 1],
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn compute_defaults(
+        ck: &Powers<Bn254>,
+        width: usize,
+        height: usize,
+    ) -> Result<(Vec<Commitment>, Vec<Fr>), VerkleError> {
+        // default_hashes[height] is the hash of an empty leaf, every level above commits to
+        // `width` copies of the hash of the level below
+        let mut default_coms = vec![Commitment::default(); height];
+        let mut default_hashes = vec![VerkleTree::hash_fr(&Fr::zero()); height + 1];
+        for depth in (0..height).rev() {
+            let evals = vec![default_hashes[depth + 1]; width];
+            let poly = VerkleTree::gen_poly_from_evals(&evals, width);
+            let (com, _) = PCS::commit(ck, &poly, None, None)?;
+            default_coms[depth] = com;
+            default_hashes[depth] = VerkleTree::hash_g1(&com.0);
+        }
+        Ok((default_coms, default_hashes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::test_rng;
+
+    #[test]
+    fn it_instantiates_tree() {
+        let mut rng = test_rng();
+        let tree = SparseVerkleTree::setup(4, 3, &mut rng).unwrap();
+        assert!(tree.nodes.is_empty());
+        assert!(tree.leaves.is_empty());
+    }
+
+    #[test]
+    fn it_matches_dense_tree_when_full() {
+        let width = 4;
+        let height = 2;
+        let mut rng = test_rng();
+        let mut tree = SparseVerkleTree::setup(width, height, &mut rng).unwrap();
+        let mut rng = test_rng();
+        let mut dense_tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=16).map(Fr::from).collect();
+        let entries: Vec<(usize, Fr)> = vec.iter().copied().enumerate().collect();
+        tree.commit(entries.as_slice()).unwrap();
+        dense_tree.commit(vec.as_slice()).unwrap();
+
+        assert_eq!(tree.root_hash(), dense_tree.root_hash().unwrap());
+    }
+
+    #[test]
+    fn it_stores_only_populated_paths() {
+        let width = 256;
+        let height = 8;
+        let mut rng = test_rng();
+        let mut tree = SparseVerkleTree::setup(width, height, &mut rng).unwrap();
+
+        let entries = vec![(7, Fr::from(1)), (usize::MAX, Fr::from(2))];
+        tree.commit(entries.as_slice()).unwrap();
+        assert_eq!(tree.leaves.len(), 2);
+        assert_eq!(tree.nodes.len(), 2 * height - 1);
+
+        tree.insert(usize::MAX, Fr::zero()).unwrap();
+        assert_eq!(tree.leaves.len(), 1);
+        assert_eq!(tree.nodes.len(), height);
+    }
+
+    #[test]
+    fn it_does_not_insert_index_out_of_range() {
+        let mut rng = test_rng();
+        let mut tree = SparseVerkleTree::setup(4, 2, &mut rng).unwrap();
+
+        let result = tree.insert(16, Fr::from(1));
+        assert!(matches!(result, Err(VerkleError::IndexOutOfRange(16))));
+        assert!(tree.leaves.is_empty());
+    }
+
+    #[test]
+    fn it_verifies_populated_and_empty_indices() {
+        let width = 16;
+        let height = 4;
+        let mut rng = test_rng();
+        let mut tree = SparseVerkleTree::setup(width, height, &mut rng).unwrap();
+
+        let entries = vec![(3, Fr::from(30)), (4000, Fr::from(40))];
+        tree.commit(entries.as_slice()).unwrap();
+
+        for index in [3, 4000, 5, 65535] {
+            let (value, multi_proof) = tree.open(index).unwrap();
+            assert_eq!(value, tree.get(index));
+            assert!(tree.verify(index, value, multi_proof).unwrap());
+        }
+    }
+
+    #[test]
+    fn it_verifies_non_power_of_two_width() {
+        let width = 10;
+        let height = 2;
+        let mut rng = test_rng();
+        let mut tree = SparseVerkleTree::setup(width, height, &mut rng).unwrap();
+
+        let entries = vec![(9, Fr::from(90)), (57, Fr::from(570))];
+        tree.commit(entries.as_slice()).unwrap();
+
+        for index in [9, 57, 99] {
+            let (value, multi_proof) = tree.open(index).unwrap();
+            assert!(tree.verify(index, value, multi_proof).unwrap());
+        }
+    }
+
+    #[test]
+    fn it_rejects_false_statement() {
+        let width = 16;
+        let height = 4;
+        let mut rng = test_rng();
+        let mut tree = SparseVerkleTree::setup(width, height, &mut rng).unwrap();
+        tree.insert(3, Fr::from(30)).unwrap();
+
+        let (value, multi_proof) = tree.open(3).unwrap();
+        assert!(!tree.verify(3, value + Fr::from(1), multi_proof).unwrap());
+
+        let (value, multi_proof) = tree.open(5).unwrap();
+        assert!(!tree.verify(5, value + Fr::from(1), multi_proof).unwrap());
+
+        // A valid proof of a tree with other values is not a proof for this root
+        let mut rng = test_rng();
+        let mut forged_tree = SparseVerkleTree::setup(width, height, &mut rng).unwrap();
+        forged_tree.insert(3, Fr::from(31)).unwrap();
+        let (value, multi_proof) = forged_tree.open(3).unwrap();
+        assert!(forged_tree.verify(3, value, multi_proof.clone()).unwrap());
+        assert!(!tree.verify(3, value, multi_proof).unwrap());
+    }
+}
@@ -0,0 +1,1079 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use ark_bn254::Fr;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::tree::{Commitment, Node};
+
+/// Position of a node in the tree. `depth` 0 is the root and `index` counts the nodes of a level
+/// from the left, so the children of `(depth, index)` are `(depth + 1, index * width + i)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeKey {
+    pub depth: usize,
+    pub index: usize,
+}
+
+impl NodeKey {
+    pub fn new(depth: usize, index: usize) -> Self {
+        Self { depth, index }
+    }
+
+    pub fn child(&self, width: usize, path_index: usize) -> Self {
+        Self::new(self.depth + 1, self.index * width + path_index)
+    }
+}
+
+pub trait NodeStore {
+    fn get(&self, key: &NodeKey) -> io::Result<Option<Node>>;
+
+    fn put(&mut self, key: NodeKey, node: Node) -> io::Result<()>;
+
+    /// Persists every node written since the last flush.
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// Writes a node of the tree that the next `replace` turns the current one into. The height of
+    /// a tree built from a stream is only known once it ends, so `key.depth` counts the levels up
+    /// from the leaves. Staged nodes are not returned by `get`.
+    fn stage(&mut self, key: NodeKey, node: Node) -> io::Result<()>;
+
+    /// Turns the current tree, which has `current_height` levels below its root, into a tree with
+    /// `counts[level]` nodes at every level counted from the leaves, and persists it. A node at a
+    /// level covers the same leaves in both trees, so only the staged nodes are written. The other
+    /// nodes move to the depth of their level in the new tree, and nodes beyond `counts` are
+    /// dropped.
+    fn replace(&mut self, current_height: Option<usize>, counts: &[usize]) -> io::Result<()>;
+
+    /// Drops the nodes staged since the last `replace`.
+    fn discard(&mut self) -> io::Result<()>;
+}
+
+/// Moves the nodes of a staged tree with `counts` nodes per level to their keys counted from the
+/// root.
+fn staged_to_depths(
+    staged: HashMap<NodeKey, Node>,
+    counts: &[usize],
+) -> impl Iterator<Item = (NodeKey, Node)> {
+    let height = counts.len() - 1;
+    staged
+        .into_iter()
+        .map(move |(key, node)| (NodeKey::new(height - key.depth, key.index), node))
+}
+
+/// Returns the key that the node at `key` of a tree of `current_height` levels has in a tree with
+/// `counts` nodes per level, or `None` if that tree has no node there.
+fn moved_key(key: &NodeKey, current_height: Option<usize>, counts: &[usize]) -> Option<NodeKey> {
+    let level = current_height?.checked_sub(key.depth)?;
+    (key.index < *counts.get(level)?).then(|| NodeKey::new(counts.len() - 1 - level, key.index))
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    pub nodes: HashMap<NodeKey, Node>,
+    staged: HashMap<NodeKey, Node>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NodeStore for MemoryStore {
+    fn get(&self, key: &NodeKey) -> io::Result<Option<Node>> {
+        Ok(self.nodes.get(key).cloned())
+    }
+
+    fn put(&mut self, key: NodeKey, node: Node) -> io::Result<()> {
+        self.nodes.insert(key, node);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn stage(&mut self, key: NodeKey, node: Node) -> io::Result<()> {
+        self.staged.insert(key, node);
+        Ok(())
+    }
+
+    fn replace(&mut self, current_height: Option<usize>, counts: &[usize]) -> io::Result<()> {
+        let nodes = std::mem::take(&mut self.nodes);
+        let staged = std::mem::take(&mut self.staged);
+        self.nodes = nodes
+            .into_iter()
+            .filter_map(|(key, node)| Some((moved_key(&key, current_height, counts)?, node)))
+            .chain(staged_to_depths(staged, counts))
+            .collect();
+        Ok(())
+    }
+
+    fn discard(&mut self) -> io::Result<()> {
+        self.staged.clear();
+        Ok(())
+    }
+}
+
+/// Keeps every version of a node that differs from the previous one, so that a commit only adds
+/// the nodes it changed and the rest of the tree is shared with earlier versions. `flush` seals
+/// the nodes written since the last flush as a new version, and a node of a version is its latest
+/// entry at or before that version. A `None` entry marks a node that a `replace` dropped.
+#[derive(Debug, Default)]
+pub struct VersionedStore {
+    pub nodes: HashMap<NodeKey, BTreeMap<u64, Option<Node>>>,
+    staged: HashMap<NodeKey, Node>,
+    pending: u64,
+    oldest: u64,
+}
+
+impl VersionedStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the latest sealed version.
+    pub fn latest_version(&self) -> Option<u64> {
+        self.pending.checked_sub(1)
+    }
+
+    pub fn contains_version(&self, version: u64) -> bool {
+        self.oldest <= version && version < self.pending
+    }
+
+    pub fn get_at(&self, version: u64, key: &NodeKey) -> Option<Node> {
+        let history = self.nodes.get(key)?;
+        history
+            .range(..=version)
+            .next_back()
+            .and_then(|(_, node)| node.clone())
+    }
+
+    /// Drops the versions before `version`. Entries that are still the latest at `version` are
+    /// kept.
+    pub fn prune(&mut self, version: u64) {
+        let version = version.min(self.pending);
+        self.nodes.retain(|_, history| {
+            let floor = history.range(..=version).next_back().map(|(v, _)| *v);
+            if let Some(floor) = floor {
+                *history = history.split_off(&floor);
+            }
+            history.values().any(Option::is_some)
+        });
+        self.oldest = self.oldest.max(version);
+    }
+
+    fn latest(&self, key: &NodeKey) -> Option<&Node> {
+        self.nodes
+            .get(key)
+            .and_then(|history| history.last_key_value())
+            .and_then(|(_, node)| node.as_ref())
+    }
+}
+
+impl NodeStore for VersionedStore {
+    fn get(&self, key: &NodeKey) -> io::Result<Option<Node>> {
+        Ok(self.latest(key).cloned())
+    }
+
+    fn put(&mut self, key: NodeKey, node: Node) -> io::Result<()> {
+        if self.latest(&key) != Some(&node) {
+            self.nodes
+                .entry(key)
+                .or_default()
+                .insert(self.pending, Some(node));
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.pending += 1;
+        Ok(())
+    }
+
+    fn stage(&mut self, key: NodeKey, node: Node) -> io::Result<()> {
+        self.staged.insert(key, node);
+        Ok(())
+    }
+
+    fn replace(&mut self, current_height: Option<usize>, counts: &[usize]) -> io::Result<()> {
+        // Nodes only get new entries if they are staged or move to another depth, the others stay
+        // shared with the previous version
+        let height = counts.len() - 1;
+        let mut nodes = HashMap::new();
+        if current_height != Some(height) {
+            for (key, history) in self.nodes.iter() {
+                let node = history.last_key_value().and_then(|(_, node)| node.as_ref());
+                if let (Some(node), Some(key)) = (node, moved_key(key, current_height, counts)) {
+                    nodes.insert(key, node.clone());
+                }
+            }
+        }
+        nodes.extend(staged_to_depths(std::mem::take(&mut self.staged), counts));
+        for (key, history) in self.nodes.iter_mut() {
+            let is_present = matches!(history.last_key_value(), Some((_, Some(_))));
+            let is_kept = moved_key(key, current_height, counts) == Some(*key);
+            if is_present && !is_kept && !nodes.contains_key(key) {
+                history.insert(self.pending, None);
+            }
+        }
+        for (key, node) in nodes {
+            self.put(key, node)?;
+        }
+        self.flush()
+    }
+
+    fn discard(&mut self) -> io::Result<()> {
+        self.staged.clear();
+        Ok(())
+    }
+}
+
+/// Offsets of the latest record of every node, by depth and index. Every level of a tree is
+/// filled from the left, so a vector per level keeps the index at 8 bytes per node.
+#[derive(Debug, Default)]
+struct LogIndex {
+    levels: Vec<Vec<u64>>,
+}
+
+const NO_RECORD: u64 = u64::MAX;
+
+impl LogIndex {
+    fn get(&self, key: &NodeKey) -> Option<u64> {
+        let offset = *self.levels.get(key.depth)?.get(key.index)?;
+        (offset != NO_RECORD).then_some(offset)
+    }
+
+    fn set(&mut self, key: NodeKey, offset: u64) {
+        if self.levels.len() <= key.depth {
+            self.levels.resize_with(key.depth + 1, Vec::new);
+        }
+        let level = &mut self.levels[key.depth];
+        if level.len() <= key.index {
+            level.resize(key.index + 1, NO_RECORD);
+        }
+        level[key.index] = offset;
+    }
+
+    fn merge(&mut self, other: LogIndex) {
+        for (depth, level) in other.levels.into_iter().enumerate() {
+            for (index, offset) in level.into_iter().enumerate() {
+                if offset != NO_RECORD {
+                    self.set(NodeKey::new(depth, index), offset);
+                }
+            }
+        }
+    }
+
+    /// Turns the index of a tree of `current_height` levels into the index of a tree with
+    /// `counts` nodes at every level, where the records of `staged`, by level from the leaves,
+    /// take the place of the nodes they replace.
+    fn reshape(&mut self, current_height: Option<usize>, counts: &[usize], staged: LogIndex) {
+        let mut levels = std::mem::take(&mut self.levels);
+        self.levels = counts
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(level, &count)| {
+                let mut offsets = current_height
+                    .and_then(|height| height.checked_sub(level))
+                    .and_then(|depth| levels.get_mut(depth))
+                    .map(std::mem::take)
+                    .unwrap_or_default();
+                offsets.resize(count, NO_RECORD);
+                let staged_offsets = staged.levels.get(level).map_or(&[][..], Vec::as_slice);
+                for (offset, &staged_offset) in offsets.iter_mut().zip(staged_offsets) {
+                    if staged_offset != NO_RECORD {
+                        *offset = staged_offset;
+                    }
+                }
+                offsets
+            })
+            .collect();
+    }
+
+    fn size_in_bytes(&self) -> usize {
+        let offsets: usize = self.levels.iter().map(Vec::capacity).sum();
+        offsets * std::mem::size_of::<u64>()
+    }
+}
+
+/// File of `(depth, index, length, node)` records that are appended through a buffer of at most
+/// `BATCH_SIZE` bytes.
+#[derive(Debug)]
+struct LogFile {
+    file: File,
+    buffer: Vec<u8>,
+    /// Length of the log, including the records in `buffer`
+    len: u64,
+}
+
+const RECORD_HEADER_SIZE: usize = 20;
+const BATCH_SIZE: usize = 1 << 20;
+
+impl LogFile {
+    fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let _ = fs::remove_file(&path);
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        Ok(Self {
+            file,
+            buffer: Vec::new(),
+            len: 0,
+        })
+    }
+
+    /// Appends a record and returns its offset.
+    fn append(&mut self, depth: u64, index: u64, payload: &[u8]) -> io::Result<u64> {
+        let record_len = RECORD_HEADER_SIZE + payload.len();
+        if self.buffer.len() + record_len > BATCH_SIZE {
+            self.file.write_all(&self.buffer)?;
+            self.buffer.clear();
+        }
+        let offset = self.len;
+        self.buffer.extend(depth.to_le_bytes());
+        self.buffer.extend(index.to_le_bytes());
+        self.buffer.extend((payload.len() as u32).to_le_bytes());
+        self.buffer.extend(payload);
+        self.len += record_len as u64;
+        Ok(offset)
+    }
+
+    /// Returns the payload of the record at `offset`.
+    fn read(&self, offset: u64) -> io::Result<Vec<u8>> {
+        let written = self.len - self.buffer.len() as u64;
+        if offset >= written {
+            let start = (offset - written) as usize + RECORD_HEADER_SIZE;
+            let node_len = u32::from_le_bytes(self.buffer[start - 4..start].try_into().unwrap());
+            return Ok(self.buffer[start..start + node_len as usize].to_vec());
+        }
+
+        let mut file = &self.file;
+        let mut header = [0u8; RECORD_HEADER_SIZE];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut header)?;
+        let node_len = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        let mut payload = vec![0u8; node_len as usize];
+        file.read_exact(&mut payload)?;
+        Ok(payload)
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.write_all(&self.buffer)?;
+        self.buffer.clear();
+        self.file.sync_data()
+    }
+}
+
+/// Append-only log of `(depth, index, length, node)` records with a compact in-memory index of
+/// the latest record of each key. `flush` ends the records written since the last flush with a
+/// marker, and records after the last marker, cut off by a crash, are dropped when the file is
+/// opened. Staged nodes are appended to the same log, so a commit only adds the nodes it changed,
+/// and `replace` ends them with a marker that holds the shape of the new tree. Records of
+/// replaced and dropped nodes stay in the log until `compact` rewrites it.
+///
+/// The index keeps 8 bytes in memory for every node of the tree, `8 * width / (width - 1)` bytes
+/// per leaf whatever the size of the nodes, so a billion leaves of width 4 need about 11 GB.
+/// `index_bytes` returns its current size, and `verkle_memory` measures it.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    log: LogFile,
+    index: LogIndex,
+    /// Offsets of the records staged since the last `replace`, by level from the leaves
+    staged: Option<LogIndex>,
+}
+
+/// `depth` of a marker record. Its `index` is `FLUSH_MARKER` after the records of a flush,
+/// `REPLACE_MARKER` after the staged records of a tree, with the height of the current tree and
+/// the number of nodes at every level of the staged one as payload, or `DISCARD_MARKER` after
+/// staged records that were dropped.
+const MARKER: u64 = u64::MAX;
+const FLUSH_MARKER: u64 = u64::MAX;
+const REPLACE_MARKER: u64 = u64::MAX - 1;
+const DISCARD_MARKER: u64 = u64::MAX - 2;
+/// Bit set in the `depth` of a staged record, whose depth is its level from the leaves.
+const STAGED: u64 = 1 << 63;
+const NO_HEIGHT: u64 = u64::MAX;
+const INTERNAL_TAG: u8 = 0;
+const LEAF_TAG: u8 = 1;
+const BYTES_TAG: u8 = 2;
+
+impl FileStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let _ = fs::remove_file(Self::compact_path(&path));
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+
+        let file_len = file.metadata()?.len();
+        let mut index = LogIndex::default();
+        let mut records = LogIndex::default();
+        let mut staged = LogIndex::default();
+        let mut reader = BufReader::new(&file);
+        let mut offset = 0;
+        let mut len = 0;
+        let mut header = [0u8; RECORD_HEADER_SIZE];
+        loop {
+            match reader.read_exact(&mut header) {
+                Ok(()) => (),
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            let depth = u64::from_le_bytes(header[0..8].try_into().unwrap());
+            let node_index = u64::from_le_bytes(header[8..16].try_into().unwrap());
+            let node_len = u32::from_le_bytes(header[16..20].try_into().unwrap());
+            let next_offset = offset + (RECORD_HEADER_SIZE + node_len as usize) as u64;
+            if next_offset > file_len {
+                break;
+            }
+
+            match (depth, node_index) {
+                (MARKER, FLUSH_MARKER) => {
+                    reader.seek_relative(node_len as i64)?;
+                    index.merge(std::mem::take(&mut records));
+                }
+                (MARKER, REPLACE_MARKER) => {
+                    let mut payload = vec![0u8; node_len as usize];
+                    reader.read_exact(&mut payload)?;
+                    let (current_height, counts) = Self::decode_shape(&payload)?;
+                    index.merge(std::mem::take(&mut records));
+                    index.reshape(current_height, &counts, std::mem::take(&mut staged));
+                }
+                (MARKER, DISCARD_MARKER) => {
+                    reader.seek_relative(node_len as i64)?;
+                    staged = LogIndex::default();
+                }
+                (MARKER, _) => {
+                    return Err(io::Error::new(ErrorKind::InvalidData, "Unknown marker"));
+                }
+                (depth, node_index) => {
+                    reader.seek_relative(node_len as i64)?;
+                    let key = NodeKey::new((depth & !STAGED) as usize, node_index as usize);
+                    if depth & STAGED != 0 {
+                        staged.set(key, offset);
+                    } else {
+                        records.set(key, offset);
+                    }
+                }
+            }
+            offset = next_offset;
+            if depth == MARKER {
+                len = offset;
+            }
+        }
+
+        // Drop the records of a flush that was cut off by a crash
+        if file_len > len {
+            file.set_len(len)?;
+        }
+
+        Ok(Self {
+            path,
+            log: LogFile {
+                file,
+                buffer: Vec::new(),
+                len,
+            },
+            index,
+            staged: None,
+        })
+    }
+
+    /// Rewrites the log with only the latest record of every node of the tree, so that replaced
+    /// and dropped nodes no longer take space on disk. Nodes that are staged are dropped.
+    pub fn compact(&mut self) -> io::Result<()> {
+        let compact_path = Self::compact_path(&self.path);
+        let mut log = LogFile::create(&compact_path)?;
+        let mut index = LogIndex::default();
+        for (depth, level) in self.index.levels.iter().enumerate() {
+            for (node_index, &offset) in level.iter().enumerate() {
+                if offset != NO_RECORD {
+                    let payload = self.log.read(offset)?;
+                    let offset = log.append(depth as u64, node_index as u64, &payload)?;
+                    index.set(NodeKey::new(depth, node_index), offset);
+                }
+            }
+        }
+        log.append(MARKER, FLUSH_MARKER, &[])?;
+        log.sync()?;
+        fs::rename(&compact_path, &self.path)?;
+        self.log = log;
+        self.index = index;
+        self.staged = None;
+        Ok(())
+    }
+
+    /// Returns the number of bytes the index of the nodes takes in memory.
+    pub fn index_bytes(&self) -> usize {
+        self.index.size_in_bytes() + self.staged.as_ref().map_or(0, LogIndex::size_in_bytes)
+    }
+
+    fn compact_path(path: &Path) -> PathBuf {
+        let mut compact_path = path.as_os_str().to_owned();
+        compact_path.push(".compact");
+        PathBuf::from(compact_path)
+    }
+
+    fn encode_shape(current_height: Option<usize>, counts: &[usize]) -> Vec<u8> {
+        let current_height = current_height.map_or(NO_HEIGHT, |height| height as u64);
+        let mut bytes = current_height.to_le_bytes().to_vec();
+        for &count in counts {
+            bytes.extend((count as u64).to_le_bytes());
+        }
+        bytes
+    }
+
+    fn decode_shape(bytes: &[u8]) -> io::Result<(Option<usize>, Vec<usize>)> {
+        if bytes.len() < 16 || !bytes.len().is_multiple_of(8) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Malformed replace marker",
+            ));
+        }
+        let mut words = bytes
+            .chunks(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()));
+        let current_height = words.next().filter(|&height| height != NO_HEIGHT);
+        Ok((
+            current_height.map(|height| height as usize),
+            words.map(|count| count as usize).collect(),
+        ))
+    }
+
+    fn encode_node(node: &Node) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let result = match node {
+            Node::Internal { value, evals } => {
+                bytes.push(INTERNAL_TAG);
+                value
+                    .serialize_compressed(&mut bytes)
+                    .and_then(|_| evals.serialize_compressed(&mut bytes))
+            }
+            Node::Leaf { value } => {
+                bytes.push(LEAF_TAG);
+                value.serialize_compressed(&mut bytes)
+            }
+            Node::Bytes { value } => {
+                bytes.push(BYTES_TAG);
+                value.serialize_compressed(&mut bytes)
+            }
+        };
+        result.map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        Ok(bytes)
+    }
+
+    fn decode_node(bytes: &[u8]) -> io::Result<Node> {
+        let (tag, mut reader) = bytes
+            .split_first()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Empty node record"))?;
+        let node = match *tag {
+            INTERNAL_TAG => Commitment::deserialize_compressed(&mut reader).and_then(|value| {
+                let evals = Vec::<Fr>::deserialize_compressed(&mut reader)?;
+                Ok(Node::Internal { value, evals })
+            }),
+            LEAF_TAG => Fr::deserialize_compressed(&mut reader).map(|value| Node::Leaf { value }),
+            BYTES_TAG => {
+                Vec::<u8>::deserialize_compressed(&mut reader).map(|value| Node::Bytes { value })
+            }
+            _ => return Err(io::Error::new(ErrorKind::InvalidData, "Unknown node tag")),
+        };
+        node.map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+impl NodeStore for FileStore {
+    fn get(&self, key: &NodeKey) -> io::Result<Option<Node>> {
+        let Some(offset) = self.index.get(key) else {
+            return Ok(None);
+        };
+        Self::decode_node(&self.log.read(offset)?).map(Some)
+    }
+
+    fn put(&mut self, key: NodeKey, node: Node) -> io::Result<()> {
+        let bytes = Self::encode_node(&node)?;
+        let offset = self
+            .log
+            .append(key.depth as u64, key.index as u64, &bytes)?;
+        self.index.set(key, offset);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.log.append(MARKER, FLUSH_MARKER, &[])?;
+        self.log.sync()
+    }
+
+    fn stage(&mut self, key: NodeKey, node: Node) -> io::Result<()> {
+        let bytes = Self::encode_node(&node)?;
+        let offset = self
+            .log
+            .append(STAGED | key.depth as u64, key.index as u64, &bytes)?;
+        self.staged
+            .get_or_insert_with(LogIndex::default)
+            .set(key, offset);
+        Ok(())
+    }
+
+    fn replace(&mut self, current_height: Option<usize>, counts: &[usize]) -> io::Result<()> {
+        let shape = Self::encode_shape(current_height, counts);
+        self.log.append(MARKER, REPLACE_MARKER, &shape)?;
+        self.log.sync()?;
+        let staged = self.staged.take().unwrap_or_default();
+        self.index.reshape(current_height, counts, staged);
+        Ok(())
+    }
+
+    fn discard(&mut self) -> io::Result<()> {
+        if self.staged.take().is_some() {
+            self.log.append(MARKER, DISCARD_MARKER, &[])?;
+            self.log.sync()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::G1Affine;
+    use ark_ec::AffineRepr;
+    use ark_poly_commit::kzg10::Commitment as KZGCommitment;
+    use std::fs;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}.log", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn it_reads_written_nodes_from_memory() {
+        let mut store = MemoryStore::new();
+        let key = NodeKey::new(1, 2);
+        store.put(key, Node::Leaf { value: Fr::from(3) }).unwrap();
+
+        assert_eq!(
+            store.get(&key).unwrap(),
+            Some(Node::Leaf { value: Fr::from(3) })
+        );
+        assert_eq!(store.get(&NodeKey::new(1, 3)).unwrap(), None);
+    }
+
+    #[test]
+    fn it_shares_unchanged_nodes_between_versions() {
+        let mut store = VersionedStore::new();
+        let key = NodeKey::new(1, 0);
+        let other_key = NodeKey::new(1, 1);
+        store.put(key, Node::Leaf { value: Fr::from(1) }).unwrap();
+        store
+            .put(other_key, Node::Leaf { value: Fr::from(2) })
+            .unwrap();
+        store.flush().unwrap();
+        store.put(key, Node::Leaf { value: Fr::from(1) }).unwrap();
+        store
+            .put(other_key, Node::Leaf { value: Fr::from(3) })
+            .unwrap();
+        store.flush().unwrap();
+
+        assert_eq!(store.latest_version(), Some(1));
+        assert_eq!(store.nodes[&key].len(), 1);
+        assert_eq!(store.nodes[&other_key].len(), 2);
+        assert_eq!(
+            store.get_at(0, &other_key),
+            Some(Node::Leaf { value: Fr::from(2) })
+        );
+        assert_eq!(
+            store.get_at(1, &other_key),
+            Some(Node::Leaf { value: Fr::from(3) })
+        );
+        assert_eq!(
+            store.get_at(1, &key),
+            Some(Node::Leaf { value: Fr::from(1) })
+        );
+    }
+
+    #[test]
+    fn it_prunes_old_versions() {
+        let mut store = VersionedStore::new();
+        let key = NodeKey::new(1, 0);
+        let shared_key = NodeKey::new(1, 1);
+        store
+            .put(shared_key, Node::Leaf { value: Fr::from(0) })
+            .unwrap();
+        for value in 1..=3 {
+            store
+                .put(
+                    key,
+                    Node::Leaf {
+                        value: Fr::from(value),
+                    },
+                )
+                .unwrap();
+            store.flush().unwrap();
+        }
+
+        store.prune(1);
+        assert!(!store.contains_version(0));
+        assert!(store.contains_version(1));
+        assert_eq!(store.nodes[&key].len(), 2);
+        assert_eq!(
+            store.get_at(1, &key),
+            Some(Node::Leaf { value: Fr::from(2) })
+        );
+        // The node written in version 0 is still part of version 1
+        assert_eq!(
+            store.get_at(1, &shared_key),
+            Some(Node::Leaf { value: Fr::from(0) })
+        );
+    }
+
+    #[test]
+    fn it_persists_nodes_across_reopen() {
+        let path = temp_path("verkle_store_reopen");
+        let internal = Node::Internal {
+            value: Commitment::default(),
+            evals: vec![Fr::from(1), Fr::from(2)],
+        };
+        let leaf = Node::Leaf { value: Fr::from(3) };
+        let bytes = Node::Bytes {
+            value: b"verkle".to_vec(),
+        };
+
+        let mut store = FileStore::open(&path).unwrap();
+        store.put(NodeKey::new(0, 0), internal.clone()).unwrap();
+        store.put(NodeKey::new(1, 1), leaf.clone()).unwrap();
+        store.put(NodeKey::new(1, 2), bytes.clone()).unwrap();
+        assert_eq!(store.get(&NodeKey::new(1, 1)).unwrap(), Some(leaf.clone()));
+        store.flush().unwrap();
+        drop(store);
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.get(&NodeKey::new(0, 0)).unwrap(), Some(internal));
+        assert_eq!(store.get(&NodeKey::new(1, 1)).unwrap(), Some(leaf));
+        assert_eq!(store.get(&NodeKey::new(1, 2)).unwrap(), Some(bytes));
+        assert_eq!(store.get(&NodeKey::new(1, 0)).unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_reads_latest_record_of_key() {
+        let path = temp_path("verkle_store_latest");
+        let key = NodeKey::new(2, 5);
+        let mut store = FileStore::open(&path).unwrap();
+        store.put(key, Node::Leaf { value: Fr::from(1) }).unwrap();
+        store.flush().unwrap();
+        let internal = Node::Internal {
+            value: KZGCommitment(G1Affine::generator()),
+            evals: vec![Fr::from(7)],
+        };
+        store.put(key, internal.clone()).unwrap();
+        store.flush().unwrap();
+        drop(store);
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.get(&key).unwrap(), Some(internal));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_ignores_unfinished_flush() {
+        let path = temp_path("verkle_store_truncated");
+        let mut store = FileStore::open(&path).unwrap();
+        store
+            .put(NodeKey::new(0, 0), Node::Leaf { value: Fr::from(1) })
+            .unwrap();
+        store.flush().unwrap();
+        store
+            .put(NodeKey::new(0, 1), Node::Leaf { value: Fr::from(2) })
+            .unwrap();
+        store.flush().unwrap();
+        drop(store);
+
+        // Cut off the marker of the second flush
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+
+        let mut store = FileStore::open(&path).unwrap();
+        assert_eq!(
+            store.get(&NodeKey::new(0, 0)).unwrap(),
+            Some(Node::Leaf { value: Fr::from(1) })
+        );
+        assert_eq!(store.get(&NodeKey::new(0, 1)).unwrap(), None);
+
+        // New records are appended after the last complete flush
+        store
+            .put(NodeKey::new(0, 1), Node::Leaf { value: Fr::from(3) })
+            .unwrap();
+        store.flush().unwrap();
+        drop(store);
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(
+            store.get(&NodeKey::new(0, 1)).unwrap(),
+            Some(Node::Leaf { value: Fr::from(3) })
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_drops_unflushed_nodes_on_reopen() {
+        let path = temp_path("verkle_store_unflushed");
+        let mut store = FileStore::open(&path).unwrap();
+        store
+            .put(NodeKey::new(0, 0), Node::Leaf { value: Fr::from(1) })
+            .unwrap();
+        assert!(store.get(&NodeKey::new(0, 0)).unwrap().is_some());
+        drop(store);
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.get(&NodeKey::new(0, 0)).unwrap(), None);
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    fn stage_leaves(store: &mut dyn NodeStore, leaves: &[(usize, u64)]) {
+        for &(index, value) in leaves {
+            let leaf = Node::Leaf {
+                value: Fr::from(value),
+            };
+            store.stage(NodeKey::new(0, index), leaf).unwrap();
+        }
+    }
+
+    fn internal_node(children: usize) -> Node {
+        Node::Internal {
+            value: Commitment::default(),
+            evals: vec![Fr::from(children as u64); children],
+        }
+    }
+
+    #[test]
+    fn it_replaces_tree_with_staged_nodes() {
+        let path = temp_path("verkle_store_replace");
+        let mut file_store = FileStore::open(&path).unwrap();
+        let mut memory_store = MemoryStore::new();
+        let mut versioned_store = VersionedStore::new();
+        let stores: [&mut dyn NodeStore; 3] =
+            [&mut file_store, &mut memory_store, &mut versioned_store];
+        for store in stores {
+            stage_leaves(store, &[(0, 1), (1, 2), (2, 3)]);
+            store.stage(NodeKey::new(1, 0), internal_node(3)).unwrap();
+            // Staged nodes are only visible once the tree is replaced
+            assert_eq!(store.get(&NodeKey::new(0, 0)).unwrap(), None);
+            store.replace(None, &[3, 1]).unwrap();
+
+            // Only the changed leaf and the root are staged, the first leaf is kept
+            stage_leaves(store, &[(1, 5)]);
+            store.stage(NodeKey::new(1, 0), internal_node(2)).unwrap();
+            store.replace(Some(1), &[2, 1]).unwrap();
+
+            assert_eq!(
+                store.get(&NodeKey::new(0, 0)).unwrap(),
+                Some(internal_node(2))
+            );
+            assert_eq!(
+                store.get(&NodeKey::new(1, 0)).unwrap(),
+                Some(Node::Leaf { value: Fr::from(1) })
+            );
+            assert_eq!(
+                store.get(&NodeKey::new(1, 1)).unwrap(),
+                Some(Node::Leaf { value: Fr::from(5) })
+            );
+            assert_eq!(store.get(&NodeKey::new(1, 2)).unwrap(), None);
+        }
+
+        // The kept leaf is shared between the versions, the dropped one is kept for its version
+        assert_eq!(versioned_store.nodes[&NodeKey::new(1, 0)].len(), 1);
+        assert_eq!(
+            versioned_store.get_at(0, &NodeKey::new(1, 2)),
+            Some(Node::Leaf { value: Fr::from(3) })
+        );
+        assert_eq!(versioned_store.get_at(1, &NodeKey::new(1, 2)), None);
+        versioned_store.prune(1);
+        assert!(!versioned_store.nodes.contains_key(&NodeKey::new(1, 2)));
+
+        drop(file_store);
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(
+            store.get(&NodeKey::new(1, 0)).unwrap(),
+            Some(Node::Leaf { value: Fr::from(1) })
+        );
+        assert_eq!(
+            store.get(&NodeKey::new(1, 1)).unwrap(),
+            Some(Node::Leaf { value: Fr::from(5) })
+        );
+        assert_eq!(store.get(&NodeKey::new(1, 2)).unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_moves_kept_nodes_to_depth_of_new_height() {
+        let path = temp_path("verkle_store_height");
+        let mut file_store = FileStore::open(&path).unwrap();
+        let mut memory_store = MemoryStore::new();
+        let mut versioned_store = VersionedStore::new();
+        let stores: [&mut dyn NodeStore; 3] =
+            [&mut file_store, &mut memory_store, &mut versioned_store];
+        for store in stores {
+            stage_leaves(store, &[(0, 1), (1, 2)]);
+            store.stage(NodeKey::new(1, 0), internal_node(2)).unwrap();
+            store.replace(None, &[2, 1]).unwrap();
+
+            // A third leaf adds a level above the current root
+            stage_leaves(store, &[(2, 3)]);
+            store.stage(NodeKey::new(1, 1), internal_node(1)).unwrap();
+            store.stage(NodeKey::new(2, 0), internal_node(2)).unwrap();
+            store.replace(Some(1), &[3, 2, 1]).unwrap();
+        }
+
+        drop(file_store);
+        let file_store = FileStore::open(&path).unwrap();
+        let stores: [&dyn NodeStore; 3] = [&file_store, &memory_store, &versioned_store];
+        for store in stores {
+            assert_eq!(
+                store.get(&NodeKey::new(0, 0)).unwrap(),
+                Some(internal_node(2))
+            );
+            assert_eq!(
+                store.get(&NodeKey::new(1, 0)).unwrap(),
+                Some(internal_node(2))
+            );
+            assert_eq!(
+                store.get(&NodeKey::new(1, 1)).unwrap(),
+                Some(internal_node(1))
+            );
+            for (index, value) in [1, 2, 3].into_iter().enumerate() {
+                assert_eq!(
+                    store.get(&NodeKey::new(2, index)).unwrap(),
+                    Some(Node::Leaf {
+                        value: Fr::from(value)
+                    })
+                );
+            }
+            assert_eq!(store.get(&NodeKey::new(1, 2)).unwrap(), None);
+        }
+        assert_eq!(
+            versioned_store.get_at(0, &NodeKey::new(1, 1)),
+            Some(Node::Leaf { value: Fr::from(2) })
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_compacts_replaced_nodes() {
+        let path = temp_path("verkle_store_compact");
+        let mut store = FileStore::open(&path).unwrap();
+        stage_leaves(&mut store, &[(0, 1), (1, 2), (2, 3), (3, 4)]);
+        store.stage(NodeKey::new(1, 0), internal_node(4)).unwrap();
+        store.replace(None, &[4, 1]).unwrap();
+        let len = fs::metadata(&path).unwrap().len();
+
+        // A commit appends the changed nodes and the marker only
+        stage_leaves(&mut store, &[(2, 5)]);
+        store.stage(NodeKey::new(1, 0), internal_node(4)).unwrap();
+        store.replace(Some(1), &[4, 1]).unwrap();
+        let leaf_len = (RECORD_HEADER_SIZE + 1 + 32) as u64;
+        let root_len = (RECORD_HEADER_SIZE + 1 + 32 + 8 + 4 * 32) as u64;
+        let marker_len = (RECORD_HEADER_SIZE + 3 * 8) as u64;
+        let appended_len = fs::metadata(&path).unwrap().len() - len;
+        assert_eq!(appended_len, leaf_len + root_len + marker_len);
+
+        store.compact().unwrap();
+        assert!(fs::metadata(&path).unwrap().len() < len);
+        drop(store);
+
+        let store = FileStore::open(&path).unwrap();
+        for (index, value) in [1, 2, 5, 4].into_iter().enumerate() {
+            assert_eq!(
+                store.get(&NodeKey::new(1, index)).unwrap(),
+                Some(Node::Leaf {
+                    value: Fr::from(value)
+                })
+            );
+        }
+        assert_eq!(
+            store.get(&NodeKey::new(0, 0)).unwrap(),
+            Some(internal_node(4))
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_writes_staged_nodes_in_batches() {
+        let path = temp_path("verkle_store_batches");
+        let mut store = FileStore::open(&path).unwrap();
+
+        // Enough nodes to fill more than one batch
+        let count = BATCH_SIZE / RECORD_HEADER_SIZE;
+        for index in 0..count {
+            let leaf = Node::Leaf {
+                value: Fr::from(index as u64),
+            };
+            store.stage(NodeKey::new(0, index), leaf).unwrap();
+        }
+        let written = fs::metadata(&path).unwrap().len() as usize;
+        assert!(written >= BATCH_SIZE);
+        assert!(store.log.buffer.len() <= BATCH_SIZE);
+        assert!(store.index_bytes() >= count * 8);
+
+        store.replace(None, &[count]).unwrap();
+        assert_eq!(
+            store.get(&NodeKey::new(0, count - 1)).unwrap(),
+            Some(Node::Leaf {
+                value: Fr::from(count as u64 - 1)
+            })
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_discards_staged_nodes() {
+        let path = temp_path("verkle_store_discard");
+        let mut store = FileStore::open(&path).unwrap();
+        store
+            .stage(NodeKey::new(0, 0), Node::Leaf { value: Fr::from(1) })
+            .unwrap();
+        store.replace(None, &[1]).unwrap();
+
+        store
+            .stage(NodeKey::new(0, 0), Node::Leaf { value: Fr::from(2) })
+            .unwrap();
+        store.discard().unwrap();
+        assert_eq!(
+            store.get(&NodeKey::new(0, 0)).unwrap(),
+            Some(Node::Leaf { value: Fr::from(1) })
+        );
+
+        // The discarded record is not taken for a staged node of the next tree
+        store
+            .stage(NodeKey::new(0, 1), Node::Leaf { value: Fr::from(3) })
+            .unwrap();
+        store.replace(Some(0), &[2]).unwrap();
+        drop(store);
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(
+            store.get(&NodeKey::new(0, 0)).unwrap(),
+            Some(Node::Leaf { value: Fr::from(1) })
+        );
+        assert_eq!(
+            store.get(&NodeKey::new(0, 1)).unwrap(),
+            Some(Node::Leaf { value: Fr::from(3) })
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_poly::EvaluationDomain;
+use ark_poly_commit::kzg10::{Powers, VerifierKey};
+
+use crate::{
+    data_structures::TransitionWitness,
+    error::VerkleError,
+    store::{NodeKey, NodeStore},
+    tree::{Commitment, Domain, Node, PCSMultipoint, Poly, VerkleTree, PCS},
+};
+
+/// Verifies `TransitionWitness`es without the tree. Besides the verifier key it needs the
+/// commitments of the Lagrange basis of the domain, so that the commitment of a node can be
+/// updated from the change of its children: `C' = C + sum((new_i - old_i) * L_i)`.
+#[derive(Debug, Clone)]
+pub struct TransitionVerifier {
+    pub vk: VerifierKey<Bn254>,
+    pub lagrange_coms: Vec<G1Affine>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl TransitionVerifier {
+    /// Verifies that applying `updates` to the tree with `root_hash` gives the tree committed to
+    /// by the last `new_coms`, and returns the hash of the new root. Returns `None` if the
+    /// witness does not hold.
+    pub fn verify(
+        &self,
+        root_hash: Fr,
+        updates: &[(usize, Fr)],
+        witness: &TransitionWitness<Bn254>,
+    ) -> Result<Option<Fr>, VerkleError> {
+        let levels = touched_positions(updates, self.height, self.width)?;
+        let length: usize = levels[..self.height].iter().map(BTreeMap::len).sum();
+        for found in [witness.coms.len(), witness.new_coms.len()] {
+            if found != length {
+                return Err(VerkleError::MalformedProof {
+                    length: found,
+                    height: self.height,
+                });
+            }
+        }
+        if witness.pre_values.len() != updates.len() {
+            return Ok(None);
+        }
+        if VerkleTree::hash_g1(&witness.coms[0].0) != root_hash {
+            return Ok(None);
+        }
+
+        // Slot of every node in `coms`, and the evaluations of the updated leaves
+        let mut slots = Vec::<HashMap<usize, usize>>::with_capacity(self.height);
+        let mut offset = 0;
+        for level in levels[..self.height].iter() {
+            slots.push(
+                level
+                    .keys()
+                    .enumerate()
+                    .map(|(i, position)| (*position, offset + i))
+                    .collect(),
+            );
+            offset += level.len();
+        }
+        let mut leaf_evals = HashMap::<usize, (Fr, Fr)>::new();
+        for (&(index, value), pre_value) in updates.iter().zip(witness.pre_values.iter()) {
+            let evals = (VerkleTree::hash_fr(pre_value), VerkleTree::hash_fr(&value));
+            leaf_evals.insert(index, evals);
+        }
+        let pre_eval = |depth: usize, child: usize| {
+            if depth + 1 == self.height {
+                leaf_evals[&child].0
+            } else {
+                VerkleTree::hash_g1(&witness.coms[slots[depth + 1][&child]].0)
+            }
+        };
+
+        let domain = Domain::new(self.width).unwrap();
+        let mut points = Vec::<Vec<Fr>>::with_capacity(length);
+        let mut values = Vec::<Vec<Fr>>::with_capacity(length);
+        for depth in 0..self.height {
+            for &position in levels[depth].keys() {
+                let first_child = position * self.width;
+                let children = levels[depth + 1].range(first_child..first_child + self.width);
+                let (node_points, node_values) = children
+                    .map(|(&child, _)| {
+                        let point = domain.element(child - first_child);
+                        (point, pre_eval(depth, child))
+                    })
+                    .unzip();
+                points.push(node_points);
+                values.push(node_values);
+            }
+        }
+        let points: Vec<&[Fr]> = points.iter().map(Vec::as_slice).collect();
+        let values: Vec<&[Fr]> = values.iter().map(Vec::as_slice).collect();
+        let result = PCSMultipoint::verify(
+            &self.vk,
+            &witness.coms,
+            &points,
+            &values,
+            &witness.multi_proof,
+        )?;
+        if !result {
+            return Ok(None);
+        }
+
+        // Update the commitments from the leaves up to the root
+        let mut new_coms = vec![G1Affine::zero(); length];
+        for depth in (0..self.height).rev() {
+            for &position in levels[depth].keys() {
+                let first_child = position * self.width;
+                let mut com = witness.coms[slots[depth][&position]].0.into_group();
+                for (&child, _) in levels[depth + 1].range(first_child..first_child + self.width) {
+                    let new_eval = if depth + 1 == self.height {
+                        leaf_evals[&child].1
+                    } else {
+                        VerkleTree::hash_g1(&new_coms[slots[depth + 1][&child]])
+                    };
+                    let delta = new_eval - pre_eval(depth, child);
+                    com += self.lagrange_coms[child - first_child] * delta;
+                }
+                new_coms[slots[depth][&position]] = com.into_affine();
+            }
+        }
+
+        let matches = new_coms
+            .iter()
+            .zip(witness.new_coms.iter())
+            .all(|(com, new_com)| *com == new_com.0);
+        Ok(matches.then(|| VerkleTree::hash_g1(&new_coms[0])))
+    }
+}
+
+impl<S: NodeStore> VerkleTree<'_, S> {
+    /// Proves that applying `updates` to the committed tree gives the tree with the updated
+    /// values. The tree itself is left unchanged.
+    pub fn prove_transition(
+        &self,
+        updates: &[(usize, Fr)],
+    ) -> Result<TransitionWitness<Bn254>, VerkleError> {
+        let height = self.height.ok_or(VerkleError::NotCommitted)?;
+        let levels = touched_positions(updates, height, self.width)?;
+        let domain = Domain::new(self.width).unwrap();
+
+        // Nodes past the end of the vector may be left over from an earlier commit, those are
+        // caught by the evaluations of their parents below
+        let mut pre_values = Vec::<Fr>::with_capacity(updates.len());
+        for &(index, _) in updates {
+            let leaf_node = self.store.get(&NodeKey::new(height, index))?;
+            match leaf_node.and_then(|node| node.leaf_value()) {
+                Some(value) => pre_values.push(value),
+                None => return Err(VerkleError::IndexOutOfRange(index)),
+            }
+        }
+
+        // Open every node on the updated paths at its updated children
+        let mut nodes = Vec::<Vec<(usize, Vec<Fr>)>>::with_capacity(height);
+        let mut polys = Vec::<Poly>::new();
+        let mut coms = Vec::<Commitment>::new();
+        let mut points = Vec::<Vec<Fr>>::new();
+        let mut values = Vec::<Vec<Fr>>::new();
+        for depth in 0..height {
+            let mut level = Vec::with_capacity(levels[depth].len());
+            for &position in levels[depth].keys() {
+                let key = NodeKey::new(depth, position);
+                let Some(Node::Internal { value, evals }) = self.store.get(&key)? else {
+                    return Err(Self::missing_node(key));
+                };
+
+                let first_child = position * self.width;
+                let mut node_points = Vec::<Fr>::new();
+                let mut node_values = Vec::<Fr>::new();
+                for (&child, &index) in
+                    levels[depth + 1].range(first_child..first_child + self.width)
+                {
+                    let Some(eval) = evals.get(child - first_child) else {
+                        return Err(VerkleError::IndexOutOfRange(index));
+                    };
+                    node_points.push(domain.element(child - first_child));
+                    node_values.push(*eval);
+                }
+                polys.push(VerkleTree::gen_poly_from_evals(&evals, self.width));
+                coms.push(value);
+                points.push(node_points);
+                values.push(node_values);
+                level.push((position, evals));
+            }
+            nodes.push(level);
+        }
+
+        // Recommit the updated paths from the leaves up to the root
+        let mut new_coms = vec![Commitment::default(); coms.len()];
+        let mut new_evals: BTreeMap<usize, Fr> = updates
+            .iter()
+            .map(|(index, value)| (*index, VerkleTree::hash_fr(value)))
+            .collect();
+        let mut offset = coms.len();
+        for depth in (0..height).rev() {
+            offset -= nodes[depth].len();
+            let mut parent_evals = BTreeMap::new();
+            for (i, (position, evals)) in nodes[depth].iter().enumerate() {
+                let first_child = position * self.width;
+                let mut evals = evals.clone();
+                for (&child, eval) in new_evals.range(first_child..first_child + self.width) {
+                    evals[child - first_child] = *eval;
+                }
+                let poly = VerkleTree::gen_poly_from_evals(&evals, self.width);
+                let (com, _) = PCS::commit(&self.ck, &poly, None, None)?;
+                new_coms[offset + i] = com;
+                parent_evals.insert(*position, VerkleTree::hash_g1(&com.0));
+            }
+            new_evals = parent_evals;
+        }
+
+        let points: Vec<&[Fr]> = points.iter().map(Vec::as_slice).collect();
+        let values: Vec<&[Fr]> = values.iter().map(Vec::as_slice).collect();
+        let multi_proof = PCSMultipoint::prove(&self.ck, &polys, &coms, &points, &values)?;
+        Ok(TransitionWitness {
+            pre_values,
+            coms,
+            new_coms,
+            multi_proof,
+        })
+    }
+
+    pub fn transition_verifier(&self) -> Result<TransitionVerifier, VerkleError> {
+        let height = self.height.ok_or(VerkleError::NotCommitted)?;
+        Ok(TransitionVerifier {
+            vk: self.vk.clone(),
+            lagrange_coms: lagrange_coms(&self.ck, self.domain_size),
+            width: self.width,
+            height,
+        })
+    }
+}
+
+/// Commits to the Lagrange basis of the domain. The coefficients of `L_i` are the inverse FFT of
+/// the `i`-th unit vector, so the commitments are the inverse FFT of the powers of `g`.
+fn lagrange_coms(ck: &Powers<Bn254>, domain_size: usize) -> Vec<G1Affine> {
+    let domain = Domain::new(domain_size).unwrap();
+    let powers: Vec<G1Projective> = ck.powers_of_g[..domain_size]
+        .iter()
+        .map(|g| g.into_group())
+        .collect();
+    G1Projective::normalize_batch(&domain.ifft(&powers))
+}
+
+/// Returns the positions of the nodes on the paths of the updated indices at every depth from
+/// the root to the leaves, each with one of the updated indices below it.
+fn touched_positions(
+    updates: &[(usize, Fr)],
+    height: usize,
+    width: usize,
+) -> Result<Vec<BTreeMap<usize, usize>>, VerkleError> {
+    let mut levels = vec![BTreeMap::new(); height + 1];
+    for &(index, _) in updates {
+        if !VerkleTree::is_in_range(index, height, width) {
+            return Err(VerkleError::IndexOutOfRange(index));
+        }
+        if levels[height].insert(index, index).is_some() {
+            return Err(VerkleError::DuplicateIndex(index));
+        }
+        let mut position = index;
+        for depth in (0..height).rev() {
+            position /= width;
+            levels[depth].entry(position).or_insert(index);
+        }
+    }
+    Ok(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::test_rng;
+
+    fn committed_tree(width: usize, n: u64) -> (VerkleTree<'static>, Vec<Fr>) {
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        (tree, vec)
+    }
+
+    #[test]
+    fn it_commits_lagrange_basis() {
+        let width = 4;
+        let (tree, _) = committed_tree(width, 4);
+        let lagrange = lagrange_coms(&tree.ck, tree.domain_size);
+
+        for (i, com) in lagrange.iter().enumerate() {
+            let mut evals = vec![Fr::from(0); width];
+            evals[i] = Fr::from(1);
+            let poly = VerkleTree::gen_poly_from_evals(&evals, width);
+            let (expected, _) = PCS::commit(&tree.ck, &poly, None, None).unwrap();
+            assert_eq!(*com, expected.0);
+        }
+    }
+
+    #[test]
+    fn it_verifies_transition() {
+        let (mut tree, mut vec) = committed_tree(4, 17);
+        let root_hash = tree.root_hash().unwrap();
+        let verifier = tree.transition_verifier().unwrap();
+
+        let updates = vec![(2, Fr::from(100)), (16, Fr::from(300)), (3, Fr::from(200))];
+        let witness = tree.prove_transition(&updates).unwrap();
+        assert_eq!(witness.pre_values, vec![vec[2], vec[16], vec[3]]);
+        let new_root_hash = verifier.verify(root_hash, &updates, &witness).unwrap();
+
+        for &(index, value) in updates.iter() {
+            vec[index] = value;
+        }
+        tree.commit(vec.as_slice()).unwrap();
+        assert_eq!(new_root_hash, Some(tree.root_hash().unwrap()));
+    }
+
+    #[test]
+    fn it_rejects_false_transition() {
+        let (tree, _) = committed_tree(4, 17);
+        let root_hash = tree.root_hash().unwrap();
+        let verifier = tree.transition_verifier().unwrap();
+
+        let updates = vec![(2, Fr::from(100)), (9, Fr::from(200))];
+        let witness = tree.prove_transition(&updates).unwrap();
+
+        let mut wrong_pre_values = witness.clone();
+        wrong_pre_values.pre_values[1] += Fr::from(1);
+        let result = verifier.verify(root_hash, &updates, &wrong_pre_values);
+        assert_eq!(result.unwrap(), None);
+
+        let mut wrong_new_coms = witness.clone();
+        wrong_new_coms.new_coms.swap(1, 2);
+        let result = verifier.verify(root_hash, &updates, &wrong_new_coms);
+        assert_eq!(result.unwrap(), None);
+
+        // The new commitments are bound to the updated values
+        let other_updates = vec![(2, Fr::from(100)), (9, Fr::from(201))];
+        let result = verifier.verify(root_hash, &other_updates, &witness);
+        assert_eq!(result.unwrap(), None);
+
+        let result = verifier.verify(root_hash + Fr::from(1), &updates, &witness);
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[test]
+    fn it_does_not_prove_invalid_updates() {
+        let (tree, _) = committed_tree(4, 17);
+
+        let result = tree.prove_transition(&[(2, Fr::from(1)), (2, Fr::from(2))]);
+        assert!(matches!(result, Err(VerkleError::DuplicateIndex(2))));
+        let result = tree.prove_transition(&[(17, Fr::from(1))]);
+        assert!(matches!(result, Err(VerkleError::IndexOutOfRange(17))));
+    }
+}
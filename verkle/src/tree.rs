@@ -3,7 +3,6 @@ use ark_ec::pairing::Pairing;
 use ark_ff::{BigInteger, PrimeField, ToConstraintField};
 use ark_poly::{
     univariate::DensePolynomial, DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain,
-    Polynomial,
 };
 use ark_poly_commit::{
     kzg10::{Commitment as KZGCommitment, Powers, VerifierKey},
@@ -11,120 +10,97 @@ use ark_poly_commit::{
 };
 use ark_std::{rand::RngCore, Zero};
 use keccak_asm::{Digest, Keccak256};
-use kzg_commitment::{kzg::KZG, multiproof::KZGMultiproof};
+use kzg_commitment::{kzg::KZG, multipoint::KZGMultipoint, multiproof::KZGMultiproof};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{
+    io::{self, ErrorKind},
+    ops::RangeInclusive,
+};
+
+use crate::{
+    data_structures::{Proof, ProofMode},
+    error::VerkleError,
+    store::{MemoryStore, NodeKey, NodeStore, VersionedStore},
+};
 
-use crate::data_structures::Proof;
+pub(crate) type Poly = DensePolynomial<Fr>;
+pub(crate) type Commitment = KZGCommitment<Bn254>;
+pub(crate) type PCS = KZG<Bn254, Poly>;
+pub(crate) type PCSMultiproof = KZGMultiproof<Bn254, Poly, Keccak256>;
+pub(crate) type PCSMultipoint = KZGMultipoint<Bn254, Poly, Keccak256>;
+pub(crate) type Domain = GeneralEvaluationDomain<Fr>;
 
-type Poly = DensePolynomial<Fr>;
-type Commitment = KZGCommitment<Bn254>;
-type PCS = KZG<Bn254, Poly>;
-type PCSMultiproof = KZGMultiproof<Bn254, Poly, Keccak256>;
-type Domain = GeneralEvaluationDomain<Fr>;
+/// Number of parents of leaves that are committed together, in parallel with the `parallel`
+/// feature, when a tree is committed.
+const PARENTS_PER_BATCH: usize = 1024;
 
-#[derive(Debug, Clone)]
+/// Node as kept in a `NodeStore`. Internal nodes keep the hashes of their children, the children
+/// themselves are stored under `NodeKey::child`. `Bytes` is a leaf committed with `commit_bytes`.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Node {
-    Internal {
-        children: Vec<Node>,
-        value: Commitment,
-        poly: Poly,
-    },
-    Leaf {
-        value: Fr,
-    },
+    Internal { value: Commitment, evals: Vec<Fr> },
+    Leaf { value: Fr },
+    Bytes { value: Vec<u8> },
+}
+
+impl Node {
+    /// Returns the field element a leaf is committed as, `VerkleTree::encode_bytes` for bytes.
+    pub fn leaf_value(&self) -> Option<Fr> {
+        match self {
+            Node::Internal { .. } => None,
+            Node::Leaf { value } => Some(*value),
+            Node::Bytes { value } => Some(VerkleTree::encode_bytes(value)),
+        }
+    }
 }
 
 #[derive(Debug)]
-pub struct VerkleTree<'a> {
+pub struct VerkleTree<'a, S: NodeStore = MemoryStore> {
     pub root: Option<Node>,
     pub height: Option<usize>,
     pub width: usize,
+    /// Size of the evaluation domain, the smallest power of two that is at least `width`. Path
+    /// points are `domain.element(i)` for `i < width`, the remaining points evaluate to zero.
+    pub domain_size: usize,
     pub ck: Powers<'a, Bn254>,
     pub vk: VerifierKey<Bn254>,
+    pub store: S,
 }
 
 impl VerkleTree<'_> {
-    pub fn setup<R: RngCore>(width: usize, rng: &mut R) -> Self {
-        let pp = PCS::setup(width, false, rng).unwrap();
-        let (ck, vk) = PCS::trim(pp, width).unwrap();
-
-        VerkleTree {
-            root: None,
-            height: None,
-            width,
-            ck,
-            vk,
-        }
-    }
-
-    pub fn print_tree(&self) {
-        self.print_node(self.root.as_ref().unwrap(), 0);
-    }
-
-    pub fn root_hash(&self) -> Fr {
-        match self.root.as_ref().unwrap() {
-            Node::Internal { value, .. } => Self::hash_g1(&value.0),
-            _ => Self::hash_fr(&Fr::from(0)),
-        }
-    }
-
-    pub fn commit(&mut self, values: &[Fr]) {
-        let leaf_nodes: Vec<Node> = values
-            .iter()
-            .map(|value| Node::Leaf {
-                value: value.clone(),
-            })
-            .collect();
-
-        self.root = Some(self.build_recursive(leaf_nodes));
-        self.height = Some(ceil_log_base(self.width, values.len()));
+    pub fn setup<R: RngCore>(width: usize, rng: &mut R) -> Result<Self, VerkleError> {
+        VerkleTree::setup_with_store(width, MemoryStore::new(), rng)
     }
 
-    pub fn open(&self, index: usize) -> Result<(Fr, Proof<Bn254>), Error> {
-        let tree_path = Self::compute_path(index, self.height.unwrap(), self.width);
-        let domain = Domain::new(self.width).unwrap();
-
-        let mut final_value = Fr::from(0);
-        let mut polys = Vec::<Poly>::new();
-        let mut coms = Vec::<Commitment>::new();
-        let mut points = Vec::<Fr>::new();
-        let mut values = Vec::<Fr>::new();
-        let mut current_node = self.root.as_ref().unwrap();
-        for (_, &path_index) in tree_path.iter().enumerate() {
-            match current_node {
-                Node::Internal {
-                    children,
-                    value,
-                    poly,
-                } => {
-                    let next_node = children.get(path_index).unwrap();
-                    match next_node {
-                        Node::Internal { value, .. } => values.push(Self::hash_g1(&value.0)),
-                        Node::Leaf { value } => {
-                            final_value = value.clone();
-                            values.push(Self::hash_fr(value))
-                        }
-                    }
-                    polys.push(poly.clone());
-                    coms.push(value.clone());
-                    points.push(domain.element(path_index));
-                    current_node = next_node;
-                }
-                _ => (),
-            }
+    /// Returns the size of the domain the nodes of a `width` tree are interpolated over.
+    pub(crate) fn domain_size(width: usize) -> Result<usize, VerkleError> {
+        match Domain::new(width) {
+            Some(domain) if width >= 2 => Ok(domain.size()),
+            _ => Err(VerkleError::InvalidWidth(width)),
         }
-
-        let multi_proof = PCSMultiproof::prove(&self.ck, &polys, &coms, &points, &values)?;
-        Ok((final_value, Proof { coms, multi_proof }))
     }
 
-    pub fn verify(
-        &self,
+    pub(crate) fn verify_path(
+        vk: &VerifierKey<Bn254>,
+        width: usize,
+        height: usize,
         index: usize,
         value: Fr,
-        multi_proof: Proof<Bn254>,
-    ) -> Result<bool, Error> {
-        let tree_path = Self::compute_path(index, self.height.unwrap(), self.width);
-        let domain = Domain::new(self.width).unwrap();
+        multi_proof: &Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        if !Self::is_in_range(index, height, width) {
+            return Err(VerkleError::IndexOutOfRange(index));
+        }
+        if multi_proof.coms.len() != height {
+            return Err(VerkleError::MalformedProof {
+                length: multi_proof.coms.len(),
+                height,
+            });
+        }
+
+        let tree_path = Self::compute_path(index, height, width);
+        let domain = Domain::new(width).unwrap();
         let points: Vec<Fr> = tree_path
             .iter()
             .map(|p| domain.element(p.clone()))
@@ -136,7 +112,7 @@ impl VerkleTree<'_> {
         }
         values.push(Self::hash_fr(&value));
         let result = PCSMultiproof::verify(
-            &self.vk,
+            vk,
             &multi_proof.coms,
             &points,
             &values,
@@ -145,7 +121,14 @@ impl VerkleTree<'_> {
         Ok(result)
     }
 
-    fn compute_path(index: usize, height: usize, width: usize) -> Vec<usize> {
+    pub(crate) fn is_in_range(index: usize, height: usize, width: usize) -> bool {
+        match width.checked_pow(height as u32) {
+            Some(size) => index < size,
+            None => true,
+        }
+    }
+
+    pub(crate) fn compute_path(index: usize, height: usize, width: usize) -> Vec<usize> {
         let mut n = index.clone();
         let mut path = vec![0; height];
         for i in (0..height).rev() {
@@ -158,40 +141,41 @@ impl VerkleTree<'_> {
         path
     }
 
-    fn build_recursive(&self, nodes: Vec<Node>) -> Node {
-        if nodes.len() <= self.width {
-            let poly = Self::gen_poly_from_nodes(&nodes, self.width);
-            let (com, _) = PCS::commit(&self.ck, &poly, None, None).unwrap();
-            return Node::Internal {
-                children: nodes,
-                value: com,
-                poly,
-            };
+    /// Returns the first and last position covered by the leaves `[start, end)` at every depth,
+    /// from the root at depth 0 down to the leaves at depth `height`.
+    pub(crate) fn range_bounds(
+        start: usize,
+        end: usize,
+        height: usize,
+        width: usize,
+    ) -> Result<Vec<(usize, usize)>, VerkleError> {
+        if start >= end {
+            return Err(VerkleError::InvalidRange { start, end });
+        }
+        if !Self::is_in_range(end - 1, height, width) {
+            return Err(VerkleError::IndexOutOfRange(end - 1));
         }
 
-        let mut parent_nodes = Vec::<Node>::new();
-        for i in (0..nodes.len()).step_by(self.width) {
-            let from_index = i;
-            let to_index = if i + self.width > nodes.len() {
-                nodes.len()
-            } else {
-                i + self.width
-            };
-            let child_nodes: Vec<Node> = nodes[from_index..to_index].to_vec();
-            let poly = Self::gen_poly_from_nodes(&child_nodes, self.width);
-            let (com, _) = PCS::commit(&self.ck, &poly, None, None).unwrap();
-            let parent_node = Node::Internal {
-                children: child_nodes,
-                value: com,
-                poly,
-            };
-            parent_nodes.push(parent_node);
+        let mut bounds = vec![(start, end - 1)];
+        for _ in 0..height {
+            let (first, last) = bounds[bounds.len() - 1];
+            bounds.push((first / width, last / width));
         }
+        bounds.reverse();
+        Ok(bounds)
+    }
 
-        self.build_recursive(parent_nodes)
+    /// Returns the children of the node at `position` that lie in `[first_child, last_child]`.
+    pub(crate) fn covered_children(
+        position: usize,
+        width: usize,
+        (first_child, last_child): (usize, usize),
+    ) -> RangeInclusive<usize> {
+        let first = position * width;
+        first.max(first_child)..=(first + width - 1).min(last_child)
     }
 
-    fn hash_g1(g1: &<Bn254 as Pairing>::G1Affine) -> Fr {
+    pub(crate) fn hash_g1(g1: &<Bn254 as Pairing>::G1Affine) -> Fr {
         let field_elements = g1.to_field_elements().unwrap();
         let mut hasher = Keccak256::new();
         for element in field_elements {
@@ -206,187 +190,1515 @@ impl VerkleTree<'_> {
         Fr::from_be_bytes_mod_order(&result.as_slice())
     }
 
-    fn hash_fr(fr: &Fr) -> Fr {
+    pub(crate) fn hash_fr(fr: &Fr) -> Fr {
         let bytes: Vec<u8> = fr.into_bigint().to_bytes_be();
         let result = Keccak256::digest(bytes.as_slice());
         Fr::from_be_bytes_mod_order(&result.as_slice())
     }
 
-    fn gen_poly_from_nodes(nodes: &Vec<Node>, domain_size: usize) -> Poly {
-        let evals: Vec<Fr> = nodes
-            .iter()
-            .map(|node| match node {
-                Node::Internal { value, .. } => Self::hash_g1(&value.0),
-                Node::Leaf { value } => Self::hash_fr(value),
+    /// Encodes a byte value of any length as the leaf it is committed as, the Keccak-256 hash of
+    /// the bytes reduced modulo the field order.
+    pub fn encode_bytes(value: &[u8]) -> Fr {
+        let result = Keccak256::digest(value);
+        Fr::from_be_bytes_mod_order(result.as_slice())
+    }
+
+    /// Commits to every chunk of evaluations of a level, tagged with the position of its parent,
+    /// and returns the parent nodes together with their evaluations in the level above.
+    #[cfg(not(feature = "parallel"))]
+    fn build_parents(
+        ck: &Powers<Bn254>,
+        width: usize,
+        chunks: Vec<(usize, Vec<Fr>)>,
+    ) -> Result<Vec<(usize, Fr, Node)>, Error> {
+        chunks
+            .into_iter()
+            .map(|(position, chunk)| {
+                let (eval, node) = Self::build_parent(ck, width, chunk)?;
+                Ok((position, eval, node))
             })
-            .collect();
-        let domain = Domain::new(domain_size).unwrap();
-        let coeffs = domain.ifft(&evals);
-        Poly::from_coefficients_vec(coeffs)
+            .collect()
     }
 
-    fn print_node(&self, node: &Node, level: usize) {
-        match node {
-            Node::Internal {
-                children,
-                value,
-                poly,
-            } => {
-                println!(
-                    "{}Internal Node - Value: {:?}, Polynomial deg: {:?}, Children size: {}",
-                    " ".repeat(level * 2),
-                    value,
-                    poly.degree(),
-                    children.len()
-                );
-                for child in children {
-                    self.print_node(child, level + 1);
-                }
-            }
-            Node::Leaf { value } => {
-                println!("{}Leaf Node - Value: {:?}", " ".repeat(level * 2), value);
-            }
-        }
+    /// Commits to every chunk of evaluations of a level, tagged with the position of its parent,
+    /// and returns the parent nodes together with their evaluations in the level above.
+    #[cfg(feature = "parallel")]
+    fn build_parents(
+        ck: &Powers<Bn254>,
+        width: usize,
+        chunks: Vec<(usize, Vec<Fr>)>,
+    ) -> Result<Vec<(usize, Fr, Node)>, Error> {
+        chunks
+            .into_par_iter()
+            .map(|(position, chunk)| {
+                let (eval, node) = Self::build_parent(ck, width, chunk)?;
+                Ok((position, eval, node))
+            })
+            .collect()
     }
-}
 
-pub fn ceil_log_base(base: usize, n: usize) -> usize {
-    if base <= 1 || n == 0 {
-        panic!("Invalid base or n")
+    fn build_parent(ck: &Powers<Bn254>, width: usize, evals: Vec<Fr>) -> Result<(Fr, Node), Error> {
+        let poly = Self::gen_poly_from_evals(&evals, width);
+        let (com, _) = PCS::commit(ck, &poly, None, None)?;
+        Ok((Self::hash_g1(&com.0), Node::Internal { value: com, evals }))
     }
 
-    let base_f64 = base as f64;
-    let n_f64 = n as f64;
-    n_f64.log(base_f64).ceil() as usize
+    pub(crate) fn gen_poly_from_evals(evals: &[Fr], domain_size: usize) -> Poly {
+        let domain = Domain::new(domain_size).unwrap();
+        let coeffs = domain.ifft(evals);
+        Poly::from_coefficients_vec(coeffs)
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use ark_bn254::Fr;
-    use ark_ff::Field;
-    use ark_std::test_rng;
+impl<S: NodeStore> VerkleTree<'_, S> {
+    /// Sets up a tree on top of `store`. If the store already holds a tree, its root and height
+    /// are loaded so that it can be opened again, which requires the same setup parameters.
+    pub fn setup_with_store<R: RngCore>(
+        width: usize,
+        store: S,
+        rng: &mut R,
+    ) -> Result<Self, VerkleError> {
+        // The interpolated polynomials have up to `domain_size` coefficients
+        let domain_size = VerkleTree::domain_size(width)?;
+        let pp = PCS::setup(domain_size, false, rng)?;
+        let (ck, vk) = PCS::trim(pp, domain_size)?;
 
-    #[test]
-    fn roots_of_unity() {
-        let domain = Domain::new(3).unwrap();
-        let element = domain.element(1);
-        println!("Element: {:?}", element);
+        let mut tree = VerkleTree {
+            root: None,
+            height: None,
+            width,
+            domain_size,
+            ck,
+            vk,
+            store,
+        };
+        tree.load_root()?;
+        Ok(tree)
+    }
 
-        let two = element.pow(&[4 as u64]);
-        println!("Two: {:?}", two);
+    pub fn print_tree(&self) {
+        self.print_node(NodeKey::new(0, 0), 0);
+    }
 
-        let element = domain.element(4);
-        println!("Element: {:?}", element);
+    pub fn root_hash(&self) -> Result<Fr, VerkleError> {
+        match self.root.as_ref() {
+            Some(Node::Internal { value, .. }) => Ok(VerkleTree::hash_g1(&value.0)),
+            Some(_) => Ok(VerkleTree::hash_fr(&Fr::from(0))),
+            None => Err(VerkleError::NotCommitted),
+        }
+    }
 
-        let domain = Domain::new(10).unwrap();
-        let element = domain.element(1);
-        println!("Element: {:?}", element);
+    pub fn root_commitment(&self) -> Option<Commitment> {
+        match self.root.as_ref()? {
+            Node::Internal { value, .. } => Some(*value),
+            _ => None,
+        }
     }
 
-    #[test]
-    fn it_instantiates_tree() {
-        let mut rng = test_rng();
-        let width = 8;
-        let tree = VerkleTree::setup(width, &mut rng);
-        assert!(tree.height.is_none());
-        assert!(tree.root.is_none());
+    pub fn commit(&mut self, values: &[Fr]) -> Result<(), VerkleError> {
+        self.commit_iter(values.iter().copied())
     }
 
-    #[test]
-    fn it_commits() {
-        let mut rng = test_rng();
-        let width = 8;
-        let mut tree = VerkleTree::setup(width, &mut rng);
+    /// Commits to `values` like `commit` without holding them in memory. Nodes are staged in the
+    /// store as soon as they are built, so only a batch of leaf evaluations and the evaluations of
+    /// the unfinished node of every level are kept. Leaves and nodes that equal those of the
+    /// current tree are not staged, so only the paths of changed leaves are committed again. The
+    /// store replaces the current tree once the last value is committed, and an error leaves the
+    /// current tree as it was.
+    pub fn commit_iter(&mut self, values: impl Iterator<Item = Fr>) -> Result<(), VerkleError> {
+        self.commit_leaves(values.map(|value| Node::Leaf { value }))
+    }
 
-        let vec = vec![Fr::from(0), Fr::from(1), Fr::from(2)];
-        tree.commit(vec.as_slice());
+    /// Commits to byte values of any length, so that the tree can hold the same data as a
+    /// `MerkleTree`. Every value is committed as `encode_bytes(value)` and `open_bytes` returns
+    /// the original bytes.
+    pub fn commit_bytes<V: AsRef<[u8]>>(&mut self, values: &[V]) -> Result<(), VerkleError> {
+        let leaves = values.iter().map(|value| Node::Bytes {
+            value: value.as_ref().to_vec(),
+        });
+        self.commit_leaves(leaves)
     }
 
-    #[test]
-    fn it_commits_bigger_vector() {
-        let n = 17;
-        let width = 4;
-        let mut rng = test_rng();
-        let mut tree = VerkleTree::setup(width, &mut rng);
+    fn commit_leaves(&mut self, leaves: impl Iterator<Item = Node>) -> Result<(), VerkleError> {
+        let result = self.stage_leaves(leaves).and_then(|counts| {
+            self.store.replace(self.height, &counts)?;
+            Ok(counts.len() - 1)
+        });
+        let height = match result {
+            Ok(height) => height,
+            Err(e) => {
+                self.store.discard()?;
+                return Err(e);
+            }
+        };
 
-        let vec: Vec<Fr> = (0..n).map(Fr::from).collect();
-        tree.commit(vec.as_slice());
-        tree.print_tree();
+        self.root = self.store.get(&NodeKey::new(0, 0))?;
+        self.height = Some(height);
+        Ok(())
     }
 
-    #[test]
-    fn it_tests_compute_path() {
-        let width = 4;
-        let height = 3;
-        let index = 2;
-        let tree_path = VerkleTree::compute_path(index, height, width);
-        println!("Tree path: {:?}", tree_path);
+    /// Stages the tree over `leaves` from the bottom up and returns the number of nodes at every
+    /// level, counted from the leaves.
+    fn stage_leaves(
+        &mut self,
+        leaves: impl Iterator<Item = Node>,
+    ) -> Result<Vec<usize>, VerkleError> {
+        // Evaluations of the nodes of every level whose parent is not built yet, and the number
+        // of nodes built at every level. The leaves are committed in batches of parents.
+        let leaf_batch = self.width * PARENTS_PER_BATCH;
+        let mut pending = vec![Vec::<Fr>::with_capacity(leaf_batch)];
+        let mut counts = vec![0];
+        for leaf_node in leaves {
+            pending[0].push(VerkleTree::hash_fr(&leaf_node.leaf_value().unwrap()));
+            if self.committed_node(0, counts[0])?.as_ref() != Some(&leaf_node) {
+                self.store.stage(NodeKey::new(0, counts[0]), leaf_node)?;
+            }
+            counts[0] += 1;
+            if pending[0].len() == leaf_batch {
+                self.build_level(&mut pending, &mut counts, 0)?;
+            }
+        }
+        if counts[0] == 0 {
+            return Err(VerkleError::EmptyVector);
+        }
+
+        // Build the nodes on the right edge, which have fewer than `width` children
+        let height = ceil_log_base(self.width, counts[0]).max(1);
+        for level in 0..height {
+            self.build_level(&mut pending, &mut counts, level)?;
+        }
+        Ok(counts)
     }
 
-    #[test]
-    fn it_computes_path_index() {
-        let width = 4;
-        let height = 3;
-        let index = 2;
-        let tree_path = VerkleTree::compute_path(index, height, width);
+    /// Returns the node of the current tree at `level`, counted from the leaves, and `index`.
+    fn committed_node(&self, level: usize, index: usize) -> Result<Option<Node>, VerkleError> {
+        match self.height {
+            Some(height) if level <= height => {
+                Ok(self.store.get(&NodeKey::new(height - level, index))?)
+            }
+            _ => Ok(None),
+        }
+    }
 
-        let domain = Domain::new(width).unwrap();
-        let roots_of_unity = domain.element(1);
-        let path_index: Vec<Fr> = tree_path
-            .iter()
-            .map(|p| domain.element(p.clone()))
-            .collect();
-        println!("Tree path: {:?}", path_index);
-        println!("Roots of unity: {:?}", roots_of_unity);
+    /// Builds the parents of the pending nodes of `level`, counted from the leaves, and the
+    /// ancestors whose last child they are.
+    fn build_level(
+        &mut self,
+        pending: &mut Vec<Vec<Fr>>,
+        counts: &mut Vec<usize>,
+        level: usize,
+    ) -> Result<(), VerkleError> {
+        if pending.len() == level + 1 {
+            pending.push(Vec::with_capacity(self.width));
+            counts.push(0);
+        }
+        // A parent whose children are unchanged is the node of the current tree, so only the
+        // parents of changed chunks are committed and staged
+        let evals = std::mem::take(&mut pending[level]);
+        let first = counts[level + 1];
+        let mut parent_evals = Vec::with_capacity(evals.len().div_ceil(self.width));
+        let mut changed = Vec::new();
+        for (position, chunk) in evals.chunks(self.width).enumerate() {
+            match self.committed_node(level + 1, first + position)? {
+                Some(Node::Internal {
+                    value,
+                    evals: children,
+                }) if children == chunk => parent_evals.push(VerkleTree::hash_g1(&value.0)),
+                _ => {
+                    changed.push((position, chunk.to_vec()));
+                    parent_evals.push(Fr::zero());
+                }
+            }
+        }
+        for (position, eval, parent_node) in
+            VerkleTree::build_parents(&self.ck, self.width, changed)?
+        {
+            parent_evals[position] = eval;
+            self.store
+                .stage(NodeKey::new(level + 1, first + position), parent_node)?;
+        }
+
+        for eval in parent_evals {
+            counts[level + 1] += 1;
+            pending[level + 1].push(eval);
+            if pending[level + 1].len() == self.width {
+                self.build_level(pending, counts, level + 1)?;
+            }
+        }
+        Ok(())
     }
 
-    #[test]
-    fn it_opens() {
-        let n = 17;
-        let width = 4;
-        let mut rng = test_rng();
-        let mut tree = VerkleTree::setup(width, &mut rng);
+    pub fn open(&self, index: usize) -> Result<(Fr, Proof<Bn254>), VerkleError> {
+        self.open_with_mode(index, ProofMode::WithRoot)
+    }
 
-        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
-        tree.commit(vec.as_slice());
+    /// Opens `index` like `open`, but with `ProofMode::OmitRoot` the root commitment is left out
+    /// of the proof. The multiproof itself still covers the root.
+    pub fn open_with_mode(
+        &self,
+        index: usize,
+        mode: ProofMode,
+    ) -> Result<(Fr, Proof<Bn254>), VerkleError> {
+        let height = self.height.ok_or(VerkleError::NotCommitted)?;
+        let (leaf_node, proof) = self.prove_path(index, height, mode, |key| self.store.get(key))?;
+        Ok((leaf_node.leaf_value().unwrap(), proof))
+    }
 
-        let index = 2;
-        let (value, _) = tree.open(index).unwrap();
-        unsafe {
-            assert_eq!(value, vec.get_unchecked(index).clone());
+    /// Opens `index` of a tree committed with `commit_bytes` and returns the original bytes.
+    pub fn open_bytes(&self, index: usize) -> Result<(Vec<u8>, Proof<Bn254>), VerkleError> {
+        let height = self.height.ok_or(VerkleError::NotCommitted)?;
+        let get_node = |key: &NodeKey| self.store.get(key);
+        match self.prove_path(index, height, ProofMode::WithRoot, get_node)? {
+            (Node::Bytes { value }, proof) => Ok((value, proof)),
+            _ => Err(VerkleError::NotBytes(index)),
         }
     }
 
-    #[test]
-    fn it_verifies() {
-        let n = 17;
-        let width = 4;
-        let mut rng = test_rng();
-        let mut tree = VerkleTree::setup(width, &mut rng);
+    pub fn verify_bytes(
+        &self,
+        index: usize,
+        value: &[u8],
+        multi_proof: Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        self.verify(index, VerkleTree::encode_bytes(value), multi_proof)
+    }
 
-        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
-        tree.commit(vec.as_slice());
+    /// Proves the path of `index` in a tree of `height` whose nodes are read with `get_node`, and
+    /// returns the leaf node with the proof.
+    fn prove_path(
+        &self,
+        index: usize,
+        height: usize,
+        mode: ProofMode,
+        get_node: impl Fn(&NodeKey) -> io::Result<Option<Node>>,
+    ) -> Result<(Node, Proof<Bn254>), VerkleError> {
+        if !VerkleTree::is_in_range(index, height, self.width) {
+            return Err(VerkleError::IndexOutOfRange(index));
+        }
+        let tree_path = VerkleTree::compute_path(index, height, self.width);
+        let domain = Domain::new(self.width).unwrap();
 
-        let index = 2;
-        let (value, multi_proof) = tree.open(index).unwrap();
-        let result = tree.verify(index, value, multi_proof);
-        assert_eq!(result.unwrap(), true);
+        let mut polys = Vec::<Poly>::new();
+        let mut coms = Vec::<Commitment>::new();
+        let mut points = Vec::<Fr>::new();
+        let mut values = Vec::<Fr>::new();
+        let mut key = NodeKey::new(0, 0);
+        let mut current_node = get_node(&key)?;
+        for &path_index in tree_path.iter() {
+            let Some(Node::Internal { value, evals }) = current_node else {
+                return Err(Self::missing_node(key));
+            };
+            // A missing child means the index is past the end of the committed vector
+            let Some(eval) = evals.get(path_index) else {
+                return Err(VerkleError::IndexOutOfRange(index));
+            };
+            values.push(*eval);
+            polys.push(VerkleTree::gen_poly_from_evals(&evals, self.width));
+            coms.push(value);
+            points.push(domain.element(path_index));
+
+            // Only the nodes along the path are loaded from the store
+            key = key.child(self.width, path_index);
+            current_node = get_node(&key)?;
+        }
+        let leaf_node = match current_node {
+            Some(node @ (Node::Leaf { .. } | Node::Bytes { .. })) => node,
+            _ => return Err(Self::missing_node(key)),
+        };
+
+        let multi_proof = PCSMultiproof::prove(&self.ck, &polys, &coms, &points, &values)?;
+        if mode == ProofMode::OmitRoot {
+            coms.remove(0);
+        }
+        Ok((leaf_node, Proof { coms, multi_proof }))
     }
 
-    #[test]
-    fn it_rejects_false_statement() {
-        let n = 17;
-        let width = 4;
-        let mut rng = test_rng();
-        let mut tree = VerkleTree::setup(width, &mut rng);
+    /// Verifies a proof against the root of this tree.
+    pub fn verify(
+        &self,
+        index: usize,
+        value: Fr,
+        multi_proof: Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        let result = VerkleTree::verify_path(
+            &self.vk,
+            self.width,
+            self.height.ok_or(VerkleError::NotCommitted)?,
+            index,
+            value,
+            &multi_proof,
+        )?;
+        Ok(result && VerkleTree::hash_g1(&multi_proof.coms[0].0) == self.root_hash()?)
+    }
 
-        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
-        tree.commit(vec.as_slice());
+    /// Verifies a proof opened with `mode`. For `ProofMode::OmitRoot` the root commitment of this
+    /// tree is put in front of the commitments of the proof.
+    pub fn verify_with_mode(
+        &self,
+        index: usize,
+        value: Fr,
+        multi_proof: Proof<Bn254>,
+        mode: ProofMode,
+    ) -> Result<bool, VerkleError> {
+        let multi_proof = match mode {
+            ProofMode::WithRoot => multi_proof,
+            ProofMode::OmitRoot => {
+                let root_commitment = self.root_commitment().ok_or(VerkleError::NotCommitted)?;
+                let mut coms = vec![root_commitment];
+                coms.extend(multi_proof.coms);
+                Proof {
+                    coms,
+                    multi_proof: multi_proof.multi_proof,
+                }
+            }
+        };
+        self.verify(index, value, multi_proof)
+    }
 
-        let index = 2;
-        let (value, multi_proof) = tree.open(index).unwrap();
-        let result = tree.verify(index, value + Fr::from(1), multi_proof);
-        assert_eq!(result.unwrap(), false);
+    /// Opens the leaves `[start, end)` at once. Every internal node whose subtree intersects the
+    /// range is opened at the points of all its covered children with a single `KZGMultipoint`
+    /// proof. `coms` holds the commitments of these nodes level by level from the root, and from
+    /// left to right within a level.
+    pub fn open_range(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<(Vec<Fr>, Proof<Bn254>), VerkleError> {
+        let height = self.height.ok_or(VerkleError::NotCommitted)?;
+        let bounds = VerkleTree::range_bounds(start, end, height, self.width)?;
+        let domain = Domain::new(self.width).unwrap();
+
+        let mut polys = Vec::<Poly>::new();
+        let mut coms = Vec::<Commitment>::new();
+        let mut points = Vec::<Vec<Fr>>::new();
+        let mut values = Vec::<Vec<Fr>>::new();
+        for depth in 0..height {
+            let (first, last) = bounds[depth];
+            for position in first..=last {
+                let key = NodeKey::new(depth, position);
+                let Some(Node::Internal { value, evals }) = self.store.get(&key)? else {
+                    return Err(Self::missing_node(key));
+                };
+
+                let children =
+                    VerkleTree::covered_children(position, self.width, bounds[depth + 1]);
+                let mut node_points = Vec::<Fr>::new();
+                let mut node_values = Vec::<Fr>::new();
+                for child in children {
+                    // A missing child means the range is past the end of the committed vector
+                    let path_index = child - position * self.width;
+                    let Some(eval) = evals.get(path_index) else {
+                        return Err(VerkleError::IndexOutOfRange(end - 1));
+                    };
+                    node_points.push(domain.element(path_index));
+                    node_values.push(*eval);
+                }
+                polys.push(VerkleTree::gen_poly_from_evals(&evals, self.width));
+                coms.push(value);
+                points.push(node_points);
+                values.push(node_values);
+            }
+        }
+
+        let mut leaves = Vec::<Fr>::with_capacity(end - start);
+        for index in start..end {
+            let key = NodeKey::new(height, index);
+            let Some(value) = self.store.get(&key)?.and_then(|node| node.leaf_value()) else {
+                return Err(Self::missing_node(key));
+            };
+            leaves.push(value);
+        }
+
+        let points: Vec<&[Fr]> = points.iter().map(Vec::as_slice).collect();
+        let values: Vec<&[Fr]> = values.iter().map(Vec::as_slice).collect();
+        let multi_proof = PCSMultipoint::prove(&self.ck, &polys, &coms, &points, &values)?;
+        Ok((leaves, Proof { coms, multi_proof }))
+    }
+
+    /// Verifies that `values` are the leaves `[start, end)` of the committed tree against a proof
+    /// output by `open_range`.
+    pub fn verify_range(
+        &self,
+        start: usize,
+        end: usize,
+        values: &[Fr],
+        multi_proof: Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        let height = self.height.ok_or(VerkleError::NotCommitted)?;
+        let bounds = VerkleTree::range_bounds(start, end, height, self.width)?;
+        if values.len() != end - start {
+            return Ok(false);
+        }
+        let length: usize = bounds[..height]
+            .iter()
+            .map(|(first, last)| last - first + 1)
+            .sum();
+        if multi_proof.coms.len() != length {
+            return Err(VerkleError::MalformedProof {
+                length: multi_proof.coms.len(),
+                height,
+            });
+        }
+
+        // The children of the nodes of a level are the commitments of the next level, and the
+        // values for the last level
+        let domain = Domain::new(self.width).unwrap();
+        let mut points = Vec::<Vec<Fr>>::new();
+        let mut evals = Vec::<Vec<Fr>>::new();
+        let mut offset = 0;
+        for depth in 0..height {
+            let (first, last) = bounds[depth];
+            let (first_child, _) = bounds[depth + 1];
+            let child_offset = offset + last - first + 1;
+            for position in first..=last {
+                let children =
+                    VerkleTree::covered_children(position, self.width, bounds[depth + 1]);
+                let mut node_points = Vec::<Fr>::new();
+                let mut node_evals = Vec::<Fr>::new();
+                for child in children {
+                    node_points.push(domain.element(child - position * self.width));
+                    node_evals.push(if depth + 1 == height {
+                        VerkleTree::hash_fr(&values[child - first_child])
+                    } else {
+                        VerkleTree::hash_g1(&multi_proof.coms[child_offset + child - first_child].0)
+                    });
+                }
+                points.push(node_points);
+                evals.push(node_evals);
+            }
+            offset = child_offset;
+        }
+
+        let points: Vec<&[Fr]> = points.iter().map(Vec::as_slice).collect();
+        let evals: Vec<&[Fr]> = evals.iter().map(Vec::as_slice).collect();
+        let result = PCSMultipoint::verify(
+            &self.vk,
+            &multi_proof.coms,
+            &points,
+            &evals,
+            &multi_proof.multi_proof,
+        )?;
+        Ok(result && Some(multi_proof.coms[0]) == self.root_commitment())
+    }
+
+    pub fn open_absence(&self, index: usize) -> Result<Proof<Bn254>, VerkleError> {
+        let tree_path = self.checked_path(index)?;
+        let domain = Domain::new(self.width).unwrap();
+
+        let mut polys = Vec::<Poly>::new();
+        let mut coms = Vec::<Commitment>::new();
+        let mut points = Vec::<Fr>::new();
+        let mut values = Vec::<Fr>::new();
+        let mut is_empty = false;
+        let mut key = NodeKey::new(0, 0);
+        let mut current_node = self.root.clone();
+        for &path_index in tree_path.iter() {
+            let Some(Node::Internal { value, evals }) = current_node else {
+                break;
+            };
+            polys.push(VerkleTree::gen_poly_from_evals(&evals, self.width));
+            coms.push(value);
+            points.push(domain.element(path_index));
+
+            // An empty child is evaluated to zero in the parent polynomial
+            match evals.get(path_index) {
+                Some(eval) => values.push(*eval),
+                None => {
+                    values.push(Fr::zero());
+                    is_empty = true;
+                    break;
+                }
+            }
+            key = key.child(self.width, path_index);
+            current_node = self.store.get(&key)?;
+        }
+        if !is_empty {
+            return Err(VerkleError::IndexNotEmpty(index));
+        }
+
+        let multi_proof = PCSMultiproof::prove(&self.ck, &polys, &coms, &points, &values)?;
+        Ok(Proof { coms, multi_proof })
+    }
+
+    pub fn verify_absence(
+        &self,
+        index: usize,
+        multi_proof: Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        let tree_path = self.checked_path(index)?;
+        let depth = multi_proof.coms.len();
+        if depth == 0 || depth > tree_path.len() {
+            return Err(VerkleError::MalformedProof {
+                length: depth,
+                height: tree_path.len(),
+            });
+        }
+
+        let domain = Domain::new(self.width).unwrap();
+        let points: Vec<Fr> = tree_path[..depth]
+            .iter()
+            .map(|p| domain.element(*p))
+            .collect();
+        let mut values: Vec<Fr> = multi_proof.coms[1..]
+            .iter()
+            .map(|com| VerkleTree::hash_g1(&com.0))
+            .collect();
+        values.push(Fr::zero());
+        let result = PCSMultiproof::verify(
+            &self.vk,
+            &multi_proof.coms,
+            &points,
+            &values,
+            &multi_proof.multi_proof,
+        )?;
+        Ok(result && VerkleTree::hash_g1(&multi_proof.coms[0].0) == self.root_hash()?)
+    }
+
+    /// Returns the path of `index` in the committed tree.
+    fn checked_path(&self, index: usize) -> Result<Vec<usize>, VerkleError> {
+        let height = self.height.ok_or(VerkleError::NotCommitted)?;
+        if !VerkleTree::is_in_range(index, height, self.width) {
+            return Err(VerkleError::IndexOutOfRange(index));
+        }
+        Ok(VerkleTree::compute_path(index, height, self.width))
+    }
+
+    pub(crate) fn missing_node(key: NodeKey) -> VerkleError {
+        VerkleError::Store(io::Error::new(
+            ErrorKind::NotFound,
+            format!("Missing node at depth {} index {}", key.depth, key.index),
+        ))
+    }
+
+    fn load_root(&mut self) -> Result<(), VerkleError> {
+        self.root = self.store.get(&NodeKey::new(0, 0))?;
+
+        // Every leaf is at the same depth, so the height is the length of the leftmost path
+        let mut height = 0;
+        let mut key = NodeKey::new(0, 0);
+        let mut current_node = self.root.clone();
+        while let Some(Node::Internal { .. }) = current_node {
+            height += 1;
+            key = key.child(self.width, 0);
+            current_node = self.store.get(&key)?;
+        }
+        self.height = if height > 0 { Some(height) } else { None };
+        Ok(())
+    }
+
+    fn print_node(&self, key: NodeKey, level: usize) {
+        match self.store.get(&key).unwrap() {
+            Some(Node::Internal { value, evals }) => {
+                println!(
+                    "{}Internal Node - Value: {:?}, Children size: {}",
+                    " ".repeat(level * 2),
+                    value,
+                    evals.len()
+                );
+                for i in 0..evals.len() {
+                    self.print_node(key.child(self.width, i), level + 1);
+                }
+            }
+            Some(Node::Leaf { value }) => {
+                println!("{}Leaf Node - Value: {:?}", " ".repeat(level * 2), value);
+            }
+            Some(Node::Bytes { value }) => {
+                println!("{}Leaf Node - Bytes: {:?}", " ".repeat(level * 2), value);
+            }
+            None => (),
+        }
+    }
+}
+
+impl VerkleTree<'_, VersionedStore> {
+    /// Returns the version of the latest commit, every commit adds a new version.
+    pub fn version(&self) -> Option<u64> {
+        self.store.latest_version()
+    }
+
+    pub fn root_hash_at(&self, version: u64) -> Result<Fr, VerkleError> {
+        self.check_version(version)?;
+        match self.store.get_at(version, &NodeKey::new(0, 0)) {
+            Some(Node::Internal { value, .. }) => Ok(VerkleTree::hash_g1(&value.0)),
+            _ => Err(Self::missing_node(NodeKey::new(0, 0))),
+        }
+    }
+
+    /// Opens `index` in the tree as it was committed in `version`.
+    pub fn open_at(&self, version: u64, index: usize) -> Result<(Fr, Proof<Bn254>), VerkleError> {
+        let height = self.height_at(version)?;
+        let (leaf_node, proof) = self.prove_path(index, height, ProofMode::WithRoot, |key| {
+            Ok(self.store.get_at(version, key))
+        })?;
+        Ok((leaf_node.leaf_value().unwrap(), proof))
+    }
+
+    /// Verifies a proof against the root of `version`.
+    pub fn verify_at(
+        &self,
+        version: u64,
+        index: usize,
+        value: Fr,
+        multi_proof: Proof<Bn254>,
+    ) -> Result<bool, VerkleError> {
+        let height = self.height_at(version)?;
+        let root_hash = self.root_hash_at(version)?;
+        let result =
+            VerkleTree::verify_path(&self.vk, self.width, height, index, value, &multi_proof)?;
+        Ok(result && VerkleTree::hash_g1(&multi_proof.coms[0].0) == root_hash)
+    }
+
+    /// Drops the versions before `version`. Nodes that are shared with `version` or later ones
+    /// are kept.
+    pub fn prune(&mut self, version: u64) -> Result<(), VerkleError> {
+        self.check_version(version)?;
+        self.store.prune(version);
+        Ok(())
+    }
+
+    fn check_version(&self, version: u64) -> Result<(), VerkleError> {
+        if !self.store.contains_version(version) {
+            return Err(VerkleError::UnknownVersion(version));
+        }
+        Ok(())
+    }
+
+    fn height_at(&self, version: u64) -> Result<usize, VerkleError> {
+        self.check_version(version)?;
+        let mut height = 0;
+        let mut key = NodeKey::new(0, 0);
+        while let Some(Node::Internal { .. }) = self.store.get_at(version, &key) {
+            height += 1;
+            key = key.child(self.width, 0);
+        }
+        Ok(height)
+    }
+}
+
+pub fn ceil_log_base(base: usize, n: usize) -> usize {
+    if base <= 1 || n == 0 {
+        panic!("Invalid base or n")
+    }
+
+    // Counts in integers, the float logarithm of an exact power such as 5^3 can round up
+    let mut height = 0;
+    let mut capacity = 1usize;
+    while capacity < n {
+        capacity = capacity.saturating_mul(base);
+        height += 1;
+    }
+    height
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::FileStore;
+    use ark_bn254::Fr;
+    use ark_ff::Field;
+    use ark_std::{str::FromStr, test_rng};
+
+    #[test]
+    fn roots_of_unity() {
+        let domain = Domain::new(3).unwrap();
+        let element = domain.element(1);
+        println!("Element: {:?}", element);
+
+        let two = element.pow(&[4 as u64]);
+        println!("Two: {:?}", two);
+
+        let element = domain.element(4);
+        println!("Element: {:?}", element);
+
+        let domain = Domain::new(10).unwrap();
+        let element = domain.element(1);
+        println!("Element: {:?}", element);
+    }
+
+    #[test]
+    fn it_instantiates_tree() {
+        let mut rng = test_rng();
+        let width = 8;
+        let tree = VerkleTree::setup(width, &mut rng).unwrap();
+        assert!(tree.height.is_none());
+        assert!(tree.root.is_none());
+    }
+
+    #[test]
+    fn it_commits() {
+        let mut rng = test_rng();
+        let width = 8;
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec = vec![Fr::from(0), Fr::from(1), Fr::from(2)];
+        tree.commit(vec.as_slice()).unwrap();
+    }
+
+    #[test]
+    fn it_commits_bigger_vector() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (0..n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        tree.print_tree();
+    }
+
+    #[test]
+    fn it_tests_compute_path() {
+        let width = 4;
+        let height = 3;
+        let index = 2;
+        let tree_path = VerkleTree::compute_path(index, height, width);
+        println!("Tree path: {:?}", tree_path);
+    }
+
+    #[test]
+    fn it_computes_path_index() {
+        let width = 4;
+        let height = 3;
+        let index = 2;
+        let tree_path = VerkleTree::compute_path(index, height, width);
+
+        let domain = Domain::new(width).unwrap();
+        let roots_of_unity = domain.element(1);
+        let path_index: Vec<Fr> = tree_path
+            .iter()
+            .map(|p| domain.element(p.clone()))
+            .collect();
+        println!("Tree path: {:?}", path_index);
+        println!("Roots of unity: {:?}", roots_of_unity);
+    }
+
+    #[test]
+    fn it_opens() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let index = 2;
+        let (value, _) = tree.open(index).unwrap();
+        unsafe {
+            assert_eq!(value, vec.get_unchecked(index).clone());
+        }
+    }
+
+    #[test]
+    fn it_verifies() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let index = 2;
+        let (value, multi_proof) = tree.open(index).unwrap();
+        let result = tree.verify(index, value, multi_proof);
+        assert_eq!(result.unwrap(), true);
+    }
+
+    #[test]
+    fn it_verifies_absence() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        // Index 18 ends in an empty leaf slot, index 40 in an empty subtree
+        for index in [18, 40] {
+            let proof = tree.open_absence(index).unwrap();
+            let result = tree.verify_absence(index, proof);
+            assert!(result.unwrap());
+        }
+    }
+
+    #[test]
+    fn it_rejects_absence_of_existing_index() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        // The proof for the empty slot 18 does not carry over to its populated sibling 16
+        let proof = tree.open_absence(18).unwrap();
+        let result = tree.verify_absence(16, proof);
+        assert!(!result.unwrap());
+    }
+
+    #[test]
+    fn it_does_not_open_absence_of_existing_index() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let result = tree.open_absence(2);
+        assert!(matches!(result, Err(VerkleError::IndexNotEmpty(2))));
+    }
+
+    #[test]
+    fn it_does_not_open_before_commit() {
+        let mut rng = test_rng();
+        let tree = VerkleTree::setup(4, &mut rng).unwrap();
+
+        assert!(matches!(tree.open(0), Err(VerkleError::NotCommitted)));
+        assert!(matches!(
+            tree.open_absence(0),
+            Err(VerkleError::NotCommitted)
+        ));
+        assert!(matches!(tree.root_hash(), Err(VerkleError::NotCommitted)));
+    }
+
+    #[test]
+    fn it_does_not_commit_empty_vector() {
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(4, &mut rng).unwrap();
+
+        assert!(matches!(tree.commit(&[]), Err(VerkleError::EmptyVector)));
+    }
+
+    #[test]
+    fn it_does_not_open_index_out_of_range() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        // Index 17 is past the end of the vector, 64 is past the capacity of the tree
+        for index in [17, 40, 64] {
+            let result = tree.open(index);
+            assert!(matches!(result, Err(VerkleError::IndexOutOfRange(i)) if i == index));
+        }
+        let result = tree.open_absence(64);
+        assert!(matches!(result, Err(VerkleError::IndexOutOfRange(64))));
+    }
+
+    #[test]
+    fn it_rejects_proof_of_wrong_length() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        // A proof without the root is one commitment short in the default mode
+        let index = 2;
+        let (value, multi_proof) = tree.open_with_mode(index, ProofMode::OmitRoot).unwrap();
+        let result = tree.verify(index, value, multi_proof);
+        assert!(matches!(
+            result,
+            Err(VerkleError::MalformedProof {
+                length: 2,
+                height: 3
+            })
+        ));
+
+        let (value, multi_proof) = tree.open(index).unwrap();
+        let result = tree.verify_with_mode(index, value, multi_proof, ProofMode::OmitRoot);
+        assert!(matches!(
+            result,
+            Err(VerkleError::MalformedProof {
+                length: 4,
+                height: 3
+            })
+        ));
+
+        let (value, multi_proof) = tree.open(index).unwrap();
+        let result = tree.verify(64, value, multi_proof);
+        assert!(matches!(result, Err(VerkleError::IndexOutOfRange(64))));
+    }
+
+    #[test]
+    fn it_builds_same_nodes_as_sequential_build() {
+        // The second size spans more than one batch of leaves
+        for (width, n) in [(4, 100), (2, 2 * PARENTS_PER_BATCH as u64 + 3)] {
+            let mut rng = test_rng();
+            let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+            let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+            tree.commit(vec.as_slice()).unwrap();
+
+            // Rebuild every level one chunk after another and compare with the stored nodes
+            let height = tree.height.unwrap();
+            let mut evals: Vec<Fr> = vec.iter().map(VerkleTree::hash_fr).collect();
+            for depth in (0..height).rev() {
+                let mut parent_evals = Vec::new();
+                for (index, chunk) in evals.chunks(width).enumerate() {
+                    let (eval, node) =
+                        VerkleTree::build_parent(&tree.ck, width, chunk.to_vec()).unwrap();
+                    let stored_node = tree.store.get(&NodeKey::new(depth, index)).unwrap();
+                    assert_eq!(stored_node, Some(node));
+                    parent_evals.push(eval);
+                }
+                evals = parent_evals;
+            }
+            assert_eq!(evals, vec![tree.root_hash().unwrap()]);
+        }
+    }
+
+    #[test]
+    fn it_recommits_smaller_vector() {
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=17).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        let vec: Vec<Fr> = (1..=5).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        assert_eq!(tree.height, Some(2));
+        // Only the 5 leaves, their 2 parents and the root are left
+        assert_eq!(tree.store.nodes.len(), 8);
+
+        let index = 3;
+        let (value, multi_proof) = tree.open(index).unwrap();
+        assert_eq!(value, vec[index]);
+        assert!(tree.verify(index, value, multi_proof).unwrap());
+    }
+
+    #[test]
+    fn it_recommits_larger_vector() {
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+        let vec: Vec<Fr> = (1..=16).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        let vec: Vec<Fr> = (1..=17).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        assert_eq!(tree.height, Some(3));
+
+        // The old root is kept one level down, next to the nodes of the new leaf
+        let mut rng = test_rng();
+        let mut new_tree = VerkleTree::setup(width, &mut rng).unwrap();
+        new_tree.commit(vec.as_slice()).unwrap();
+        assert_eq!(tree.root_hash().unwrap(), new_tree.root_hash().unwrap());
+        assert_eq!(tree.store.nodes, new_tree.store.nodes);
+
+        let index = 16;
+        let (value, multi_proof) = tree.open(index).unwrap();
+        assert_eq!(value, vec[index]);
+        assert!(tree.verify(index, value, multi_proof).unwrap());
+    }
+
+    #[test]
+    fn it_commits_iter_with_same_root() {
+        let width = 4;
+        for n in [1, 4, 16, 17, 50] {
+            let mut rng = test_rng();
+            let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+            let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+            tree.commit(vec.as_slice()).unwrap();
+
+            let mut rng = test_rng();
+            let mut streamed_tree = VerkleTree::setup(width, &mut rng).unwrap();
+            streamed_tree.commit_iter((1..=n).map(Fr::from)).unwrap();
+
+            assert_eq!(streamed_tree.height, tree.height);
+            assert_eq!(
+                streamed_tree.root_hash().unwrap(),
+                tree.root_hash().unwrap()
+            );
+            assert_eq!(streamed_tree.store.nodes, tree.store.nodes);
+        }
+    }
+
+    #[test]
+    fn it_commits_iter_of_unknown_length() {
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+        let vec: Vec<Fr> = (1..=17).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let mut rng = test_rng();
+        let mut streamed_tree = VerkleTree::setup(width, &mut rng).unwrap();
+        let values = (1..=17u64).map(Fr::from).filter(|_| true);
+        assert_eq!(values.size_hint(), (0, Some(17)));
+        streamed_tree.commit_iter(values).unwrap();
+        assert_eq!(streamed_tree.height, tree.height);
+        assert_eq!(
+            streamed_tree.root_hash().unwrap(),
+            tree.root_hash().unwrap()
+        );
+
+        let result = streamed_tree.commit_iter(std::iter::empty());
+        assert!(matches!(result, Err(VerkleError::EmptyVector)));
+        assert_eq!(
+            streamed_tree.root_hash().unwrap(),
+            tree.root_hash().unwrap()
+        );
+    }
+
+    /// Store that fails to stage nodes once `stages_left` is used up.
+    struct FailingStore {
+        inner: MemoryStore,
+        stages_left: usize,
+    }
+
+    impl NodeStore for FailingStore {
+        fn get(&self, key: &NodeKey) -> io::Result<Option<Node>> {
+            self.inner.get(key)
+        }
+
+        fn put(&mut self, key: NodeKey, node: Node) -> io::Result<()> {
+            self.inner.put(key, node)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+
+        fn stage(&mut self, key: NodeKey, node: Node) -> io::Result<()> {
+            if self.stages_left == 0 {
+                return Err(io::Error::other("Store is full"));
+            }
+            self.stages_left -= 1;
+            self.inner.stage(key, node)
+        }
+
+        fn replace(&mut self, current_height: Option<usize>, counts: &[usize]) -> io::Result<()> {
+            self.inner.replace(current_height, counts)
+        }
+
+        fn discard(&mut self) -> io::Result<()> {
+            self.inner.discard()
+        }
+    }
+
+    #[test]
+    fn it_keeps_tree_when_commit_fails() {
+        let width = 4;
+        let mut rng = test_rng();
+        let store = FailingStore {
+            inner: MemoryStore::new(),
+            stages_left: usize::MAX,
+        };
+        let mut tree = VerkleTree::setup_with_store(width, store, &mut rng).unwrap();
+        let vec: Vec<Fr> = (1..=17).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        let root_hash = tree.root_hash().unwrap();
+        let nodes = tree.store.inner.nodes.clone();
+
+        // Fail part-way through the leaves of a larger tree
+        tree.store.stages_left = 30;
+        let new_vec: Vec<Fr> = (100..=200).map(Fr::from).collect();
+        let result = tree.commit(new_vec.as_slice());
+        assert!(matches!(result, Err(VerkleError::Store(_))));
+        assert_eq!(tree.height, Some(3));
+        assert_eq!(tree.root_hash().unwrap(), root_hash);
+        assert_eq!(tree.store.inner.nodes, nodes);
+
+        let (value, multi_proof) = tree.open(16).unwrap();
+        assert_eq!(value, vec[16]);
+        assert!(tree.verify(16, value, multi_proof).unwrap());
+
+        // Nothing of the failed commit is left staged
+        tree.store.stages_left = usize::MAX;
+        tree.commit(&vec[..5]).unwrap();
+        assert_eq!(tree.store.inner.nodes.len(), 8);
+    }
+
+    #[test]
+    fn it_opens_bytes() {
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Vec<u8>> = (0..17u8).map(|n| vec![n; n as usize * 3]).collect();
+        tree.commit_bytes(vec.as_slice()).unwrap();
+
+        for index in [0, 11, 16] {
+            let (value, multi_proof) = tree.open_bytes(index).unwrap();
+            assert_eq!(value, vec[index]);
+            assert!(tree
+                .verify_bytes(index, &value, multi_proof.clone())
+                .unwrap());
+            assert!(!tree.verify_bytes(index, &[1, 2, 3], multi_proof).unwrap());
+        }
+
+        // The leaves are committed as field elements, so `open` and `verify` work as well
+        let (value, multi_proof) = tree.open(5).unwrap();
+        assert_eq!(value, VerkleTree::encode_bytes(&vec[5]));
+        assert!(tree.verify(5, value, multi_proof).unwrap());
+    }
+
+    #[test]
+    fn it_does_not_open_bytes_of_field_leaf() {
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(4, &mut rng).unwrap();
+        let vec: Vec<Fr> = (1..=4).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let result = tree.open_bytes(2);
+        assert!(matches!(result, Err(VerkleError::NotBytes(2))));
+    }
+
+    #[test]
+    fn it_reopens_tree_from_file_store() {
+        let path = std::env::temp_dir().join(format!("verkle_tree_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let width = 4;
+        let vec: Vec<Fr> = (1..=17).map(Fr::from).collect();
+
+        let mut rng = test_rng();
+        let store = FileStore::open(&path).unwrap();
+        let mut tree = VerkleTree::setup_with_store(width, store, &mut rng).unwrap();
+        tree.commit(vec.as_slice()).unwrap();
+        let root_hash = tree.root_hash().unwrap();
+        drop(tree);
+
+        // Setting up with the same parameters restores the committed tree
+        let mut rng = test_rng();
+        let store = FileStore::open(&path).unwrap();
+        let tree = VerkleTree::setup_with_store(width, store, &mut rng).unwrap();
+        assert_eq!(tree.height, Some(3));
+        assert_eq!(tree.root_hash().unwrap(), root_hash);
+
+        let index = 10;
+        let (value, multi_proof) = tree.open(index).unwrap();
+        assert_eq!(value, vec[index]);
+        assert!(tree.verify(index, value, multi_proof).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_recommits_smaller_vector_to_file_store() {
+        let path =
+            std::env::temp_dir().join(format!("verkle_tree_recommit_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let width = 4;
+
+        let mut rng = test_rng();
+        let store = FileStore::open(&path).unwrap();
+        let mut tree = VerkleTree::setup_with_store(width, store, &mut rng).unwrap();
+        let vec: Vec<Fr> = (1..=17).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        let len = std::fs::metadata(&path).unwrap().len();
+        let vec: Vec<Fr> = (1..=5).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        tree.store.compact().unwrap();
+        drop(tree);
+
+        // The nodes of the larger tree are not left in the compacted log
+        assert!(std::fs::metadata(&path).unwrap().len() < len);
+        let mut rng = test_rng();
+        let store = FileStore::open(&path).unwrap();
+        let tree = VerkleTree::setup_with_store(width, store, &mut rng).unwrap();
+        assert_eq!(tree.height, Some(2));
+        assert_eq!(tree.store.get(&NodeKey::new(3, 16)).unwrap(), None);
+        let (value, multi_proof) = tree.open(4).unwrap();
+        assert_eq!(value, vec[4]);
+        assert!(tree.verify(4, value, multi_proof).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_appends_changed_path_to_file_store() {
+        let path =
+            std::env::temp_dir().join(format!("verkle_tree_update_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let width = 4;
+
+        let mut rng = test_rng();
+        let store = FileStore::open(&path).unwrap();
+        let mut tree = VerkleTree::setup_with_store(width, store, &mut rng).unwrap();
+        let mut vec: Vec<Fr> = (1..=64).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        let len = std::fs::metadata(&path).unwrap().len();
+
+        // Only the leaf and the 3 nodes on its path are appended
+        vec[37] = Fr::from(100);
+        tree.commit(vec.as_slice()).unwrap();
+        let appended_len = std::fs::metadata(&path).unwrap().len() - len;
+        assert!(appended_len < len / 10);
+        drop(tree);
+
+        let mut rng = test_rng();
+        let store = FileStore::open(&path).unwrap();
+        let tree = VerkleTree::setup_with_store(width, store, &mut rng).unwrap();
+        for index in [36, 37] {
+            let (value, multi_proof) = tree.open(index).unwrap();
+            assert_eq!(value, vec[index]);
+            assert!(tree.verify(index, value, multi_proof).unwrap());
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_verifies_proof_without_root() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let index = 2;
+        let (_, full_proof) = tree.open(index).unwrap();
+        let (value, multi_proof) = tree.open_with_mode(index, ProofMode::OmitRoot).unwrap();
+        assert_eq!(multi_proof.coms.len(), full_proof.coms.len() - 1);
+        assert_eq!(multi_proof.coms[..], full_proof.coms[1..]);
+
+        let result = tree.verify_with_mode(index, value, multi_proof.clone(), ProofMode::OmitRoot);
+        assert!(result.unwrap());
+        let result =
+            tree.verify_with_mode(index, value + Fr::from(1), multi_proof, ProofMode::OmitRoot);
+        assert!(!result.unwrap());
+    }
+
+    #[test]
+    fn it_rejects_proof_without_root_against_other_root() {
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=17).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+        let index = 2;
+        let (value, multi_proof) = tree.open_with_mode(index, ProofMode::OmitRoot).unwrap();
+
+        // The inner commitments are unchanged, only the root differs
+        let mut vec = vec;
+        vec[16] = Fr::from(100);
+        tree.commit(vec.as_slice()).unwrap();
+        let result = tree.verify_with_mode(index, value, multi_proof, ProofMode::OmitRoot);
+        assert!(!result.unwrap());
+    }
+
+    #[test]
+    fn it_verifies_non_power_of_two_width() {
+        for (width, n, height) in [(3, 20u64, 3), (10, 150, 3), (5, 125, 3)] {
+            let mut rng = test_rng();
+            let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+            assert_eq!(tree.domain_size, width.next_power_of_two());
+
+            let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+            tree.commit(vec.as_slice()).unwrap();
+            assert_eq!(tree.height, Some(height));
+
+            for index in [0, width - 1, width, n as usize - 1] {
+                let (value, multi_proof) = tree.open(index).unwrap();
+                assert_eq!(value, vec[index]);
+                assert!(tree.verify(index, value, multi_proof).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn it_uses_contract_path_points_for_non_power_of_two_width() {
+        let width = 10;
+        let height = 2;
+        let index = 19;
+        let domain = Domain::new(width).unwrap();
+
+        // `VerkleVerifier` is deployed with `domain.element(1)` and computes the point of a path
+        // index `i` as its `i`-th power, which is the 16th root of unity used by the tree
+        let roots_of_unity = domain.element(1);
+        assert_eq!(
+            roots_of_unity,
+            Fr::from_str(
+                "14940766826517323942636479241147756311199852622225275649687664389641784935947"
+            )
+            .unwrap()
+        );
+        for path_index in VerkleTree::compute_path(index, height, width) {
+            assert_eq!(
+                domain.element(path_index),
+                roots_of_unity.pow([path_index as u64])
+            );
+        }
+    }
+
+    #[test]
+    fn it_rejects_width_below_two() {
+        let mut rng = test_rng();
+        let result = VerkleTree::setup(1, &mut rng);
+        assert!(matches!(result, Err(VerkleError::InvalidWidth(1))));
+    }
+
+    #[test]
+    fn it_verifies_range() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        for (start, end) in [(3, 14), (0, 17), (16, 17), (5, 6), (4, 8)] {
+            let (values, multi_proof) = tree.open_range(start, end).unwrap();
+            assert_eq!(values, vec[start..end]);
+            assert!(tree.verify_range(start, end, &values, multi_proof).unwrap());
+        }
+
+        // The first 16 leaves share 6 commitments instead of 3 for each leaf
+        let (_, multi_proof) = tree.open_range(0, 16).unwrap();
+        assert_eq!(multi_proof.coms.len(), 6);
+    }
+
+    #[test]
+    fn it_rejects_false_range_statement() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let (start, end) = (3, 14);
+        let (mut values, multi_proof) = tree.open_range(start, end).unwrap();
+        values[5] += Fr::from(1);
+        assert!(!tree
+            .verify_range(start, end, &values, multi_proof.clone())
+            .unwrap());
+
+        let values = vec[start..end - 1].to_vec();
+        assert!(!tree.verify_range(start, end, &values, multi_proof).unwrap());
+
+        // The proof does not carry over to a shifted range with the same commitments
+        let (_, multi_proof) = tree.open_range(1, 3).unwrap();
+        let values = vec[2..4].to_vec();
+        assert!(!tree.verify_range(2, 4, &values, multi_proof).unwrap());
+    }
+
+    #[test]
+    fn it_does_not_open_invalid_range() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let result = tree.open_range(10, 18);
+        assert!(matches!(result, Err(VerkleError::IndexOutOfRange(17))));
+        let result = tree.open_range(0, 65);
+        assert!(matches!(result, Err(VerkleError::IndexOutOfRange(64))));
+        let result = tree.open_range(3, 3);
+        assert!(matches!(
+            result,
+            Err(VerkleError::InvalidRange { start: 3, end: 3 })
+        ));
+    }
+
+    #[test]
+    fn it_opens_past_versions() {
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree =
+            VerkleTree::setup_with_store(width, VersionedStore::new(), &mut rng).unwrap();
+
+        let old_vec: Vec<Fr> = (1..=17).map(Fr::from).collect();
+        tree.commit(old_vec.as_slice()).unwrap();
+        let old_root_hash = tree.root_hash().unwrap();
+        let entries: usize = tree.store.nodes.values().map(|history| history.len()).sum();
+
+        let mut vec = old_vec.clone();
+        vec[5] = Fr::from(100);
+        tree.commit(vec.as_slice()).unwrap();
+        assert_eq!(tree.version(), Some(1));
+
+        // Only the leaf and the nodes on its path are added
+        let new_entries: usize = tree.store.nodes.values().map(|history| history.len()).sum();
+        assert_eq!(new_entries, entries + 4);
+
+        assert_eq!(tree.root_hash_at(0).unwrap(), old_root_hash);
+        assert_eq!(tree.root_hash_at(1).unwrap(), tree.root_hash().unwrap());
+        for (version, vec) in [(0, &old_vec), (1, &vec)] {
+            for index in [5, 6] {
+                let (value, multi_proof) = tree.open_at(version, index).unwrap();
+                assert_eq!(value, vec[index]);
+                assert!(tree.verify_at(version, index, value, multi_proof).unwrap());
+            }
+        }
+
+        // A proof of the old value does not verify against the new root
+        let (value, multi_proof) = tree.open_at(0, 5).unwrap();
+        assert!(!tree.verify_at(1, 5, value, multi_proof).unwrap());
+    }
+
+    #[test]
+    fn it_does_not_open_pruned_version() {
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree =
+            VerkleTree::setup_with_store(width, VersionedStore::new(), &mut rng).unwrap();
+
+        let mut vec: Vec<Fr> = (1..=17).map(Fr::from).collect();
+        for version in 0..3 {
+            vec[0] = Fr::from(100 + version);
+            tree.commit(vec.as_slice()).unwrap();
+        }
+        tree.prune(2).unwrap();
+
+        assert!(matches!(
+            tree.open_at(1, 0),
+            Err(VerkleError::UnknownVersion(1))
+        ));
+        assert!(matches!(
+            tree.open_at(3, 0),
+            Err(VerkleError::UnknownVersion(3))
+        ));
+
+        // Nodes written in version 0 and not changed since are still there
+        let index = 16;
+        let (value, multi_proof) = tree.open_at(2, index).unwrap();
+        assert_eq!(value, vec[index]);
+        assert!(tree.verify_at(2, index, value, multi_proof).unwrap());
+    }
+
+    #[test]
+    fn it_rejects_false_statement() {
+        let n = 17;
+        let width = 4;
+        let mut rng = test_rng();
+        let mut tree = VerkleTree::setup(width, &mut rng).unwrap();
+
+        let vec: Vec<Fr> = (1..=n).map(Fr::from).collect();
+        tree.commit(vec.as_slice()).unwrap();
+
+        let index = 2;
+        let (value, multi_proof) = tree.open(index).unwrap();
+        let result = tree.verify(index, value + Fr::from(1), multi_proof);
+        assert_eq!(result.unwrap(), false);
+
+        // A valid proof of a tree with other values is not a proof for this root
+        let mut rng = test_rng();
+        let mut forged_tree = VerkleTree::setup(width, &mut rng).unwrap();
+        let forged_vec: Vec<Fr> = (1..=n).map(|x| Fr::from(x + 1)).collect();
+        forged_tree.commit(forged_vec.as_slice()).unwrap();
+        let (value, multi_proof) = forged_tree.open(index).unwrap();
+        assert!(forged_tree
+            .verify(index, value, multi_proof.clone())
+            .unwrap());
+        assert!(!tree.verify(index, value, multi_proof.clone()).unwrap());
+        let result = tree.verify_with_mode(index, value, multi_proof, ProofMode::WithRoot);
+        assert!(!result.unwrap());
     }
 }